@@ -0,0 +1,277 @@
+use crate::error::{Error, Result};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub trait ClipboardBackend {
+    fn name(&self) -> &'static str;
+    fn is_available(&self) -> bool;
+    fn copy(&self, text: &str) -> io::Result<()>;
+}
+
+/// Backend names accepted by `--clipboard` and `ONELINERS_CLIPBOARD`, in
+/// auto-detection order.
+pub const BACKENDS: &[&str] = &["wl-copy", "xclip", "xsel", "tmux", "osc52", "stdout"];
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+fn has_env(name: &str) -> bool {
+    env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} exited with {}", program, status)))
+    }
+}
+
+pub struct WlCopy;
+
+impl ClipboardBackend for WlCopy {
+    fn name(&self) -> &'static str {
+        "wl-copy"
+    }
+
+    fn is_available(&self) -> bool {
+        has_env("WAYLAND_DISPLAY") && in_path("wl-copy")
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        pipe_to("wl-copy", &[], text)
+    }
+}
+
+pub struct Xclip;
+
+impl ClipboardBackend for Xclip {
+    fn name(&self) -> &'static str {
+        "xclip"
+    }
+
+    fn is_available(&self) -> bool {
+        has_env("DISPLAY") && in_path("xclip")
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        pipe_to("xclip", &["-selection", "clipboard"], text)
+    }
+}
+
+pub struct Xsel;
+
+impl ClipboardBackend for Xsel {
+    fn name(&self) -> &'static str {
+        "xsel"
+    }
+
+    fn is_available(&self) -> bool {
+        has_env("DISPLAY") && in_path("xsel")
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        pipe_to("xsel", &["--clipboard", "--input"], text)
+    }
+}
+
+pub struct Tmux;
+
+impl ClipboardBackend for Tmux {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn is_available(&self) -> bool {
+        has_env("TMUX") && in_path("tmux")
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        let status = Command::new("tmux").args(["set-buffer", "--", text]).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("tmux exited with {}", status)))
+        }
+    }
+}
+
+/// Sets the clipboard of the terminal emulator itself with an OSC 52
+/// escape sequence, which also works over SSH.
+pub struct Osc52;
+
+impl ClipboardBackend for Osc52 {
+    fn name(&self) -> &'static str {
+        "osc52"
+    }
+
+    fn is_available(&self) -> bool {
+        OpenOptions::new().write(true).open("/dev/tty").is_ok()
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        let mut sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+        if has_env("TMUX") {
+            // tmux only forwards escape sequences wrapped in a passthrough.
+            sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+        }
+        let mut tty = OpenOptions::new().write(true).open("/dev/tty")?;
+        tty.write_all(sequence.as_bytes())?;
+        tty.flush()
+    }
+}
+
+/// Prints the snippet so it can be copied by hand or piped elsewhere.
+pub struct Stdout;
+
+impl ClipboardBackend for Stdout {
+    fn name(&self) -> &'static str {
+        "stdout"
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        out.write_all(text.as_bytes())?;
+        if !text.ends_with('\n') && out.is_terminal() {
+            out.write_all(b"\n")?;
+        }
+        out.flush()
+    }
+}
+
+pub fn backend_by_name(name: &str) -> Option<Box<dyn ClipboardBackend>> {
+    let backend: Box<dyn ClipboardBackend> = match name {
+        "wl-copy" | "wayland" => Box::new(WlCopy),
+        "xclip" => Box::new(Xclip),
+        "xsel" => Box::new(Xsel),
+        "tmux" => Box::new(Tmux),
+        "osc52" => Box::new(Osc52),
+        "stdout" => Box::new(Stdout),
+        _ => return None,
+    };
+    Some(backend)
+}
+
+/// The first available backend in `BACKENDS` order. `stdout` always is.
+pub fn detect() -> Box<dyn ClipboardBackend> {
+    first_available(BACKENDS.iter().filter_map(|name| backend_by_name(name)))
+}
+
+fn first_available(backends: impl IntoIterator<Item = Box<dyn ClipboardBackend>>) -> Box<dyn ClipboardBackend> {
+    backends.into_iter().find(|backend| backend.is_available()).unwrap_or_else(|| Box::new(Stdout))
+}
+
+/// Picks the backend named by `preference`, the `clipboard` setting,
+/// auto-detecting when it is unset or `auto`.
+pub fn resolve(preference: Option<&str>) -> Result<Box<dyn ClipboardBackend>> {
+    let name = preference.map(str::trim).unwrap_or("auto");
+    if name.is_empty() || name == "auto" {
+        return Ok(detect());
+    }
+
+    let backend = backend_by_name(name).ok_or_else(|| {
+        Error::Invalid(format!("unknown clipboard backend '{}'; choose one of: auto, {}", name, BACKENDS.join(", ")))
+    })?;
+    if !backend.is_available() {
+        return Err(Error::Invalid(format!("clipboard backend '{}' is not available here", name)));
+    }
+    Ok(backend)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake(&'static str, bool);
+
+    impl ClipboardBackend for Fake {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn is_available(&self) -> bool {
+            self.1
+        }
+
+        fn copy(&self, _: &str) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn fakes(backends: &[(&'static str, bool)]) -> Vec<Box<dyn ClipboardBackend>> {
+        backends.iter().map(|&(name, available)| Box::new(Fake(name, available)) as Box<dyn ClipboardBackend>).collect()
+    }
+
+    #[test]
+    fn base64_pads_short_tails() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Many"), "TWFueQ==");
+        assert_eq!(base64(b"Many "), "TWFueSA=");
+        assert_eq!(base64(b"ls -la | grep \xff\x00"), "bHMgLWxhIHwgZ3JlcCD/AA==");
+    }
+
+    #[test]
+    fn detection_takes_the_first_available_backend() {
+        let picked = first_available(fakes(&[("wl-copy", false), ("xclip", true), ("xsel", true)]));
+        assert_eq!(picked.name(), "xclip");
+        assert_eq!(first_available(fakes(&[("wl-copy", false), ("tmux", false)])).name(), "stdout");
+
+        let order: Vec<&str> = BACKENDS.iter().filter_map(|name| backend_by_name(name)).map(|backend| backend.name()).collect();
+        assert_eq!(order, BACKENDS);
+        assert_eq!(backend_by_name("wayland").unwrap().name(), "wl-copy");
+    }
+
+    #[test]
+    fn resolves_the_configured_backend() {
+        assert_eq!(resolve(Some("stdout")).unwrap().name(), "stdout");
+        assert_eq!(resolve(Some(" stdout ")).unwrap().name(), "stdout");
+        let error = resolve(Some("pbcopy")).err().unwrap();
+        assert_eq!(error.to_string(), "unknown clipboard backend 'pbcopy'; choose one of: auto, wl-copy, xclip, xsel, tmux, osc52, stdout");
+        assert_eq!(resolve(None).unwrap().name(), detect().name());
+        assert_eq!(resolve(Some("auto")).unwrap().name(), detect().name());
+    }
+}
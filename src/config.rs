@@ -0,0 +1,565 @@
+use crate::clipboard;
+use crate::error::{Context, Error, Result};
+use crate::paths;
+use crate::search::{self, Mode};
+use crate::store;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Config file shared by every user of the machine.
+pub const SYSTEM_FILE: &str = "/etc/oneliners/config.toml";
+
+/// Config file for a directory tree, found in the current directory or the
+/// nearest parent that has one.
+pub const PROJECT_FILE: &str = ".oneliners.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Count,
+    Choice(&'static [&'static str]),
+    /// `auto` or one of `clipboard::BACKENDS`.
+    Clipboard,
+}
+
+/// A setting that can be configured, and how.
+#[derive(Debug)]
+pub struct Key {
+    pub name: &'static str,
+    /// Environment variable that overrides the config files.
+    pub env: &'static str,
+    default: Option<&'static str>,
+    kind: Kind,
+    /// Whether a project file may set it. Settings that decide what runs
+    /// unasked, or which file gets rewritten, stay out of reach of a file
+    /// that came with a cloned repo.
+    project: bool,
+}
+
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "store",
+        env: paths::STORE_ENV,
+        default: None,
+        kind: Kind::Text,
+        project: false,
+    },
+    Key {
+        name: "limit",
+        env: "ONELINERS_LIMIT",
+        default: Some("10"),
+        kind: Kind::Count,
+        project: true,
+    },
+    Key {
+        name: "clipboard",
+        env: "ONELINERS_CLIPBOARD",
+        default: Some("auto"),
+        kind: Kind::Clipboard,
+        project: true,
+    },
+    Key {
+        name: "shell",
+        env: "ONELINERS_SHELL",
+        default: None,
+        kind: Kind::Text,
+        project: false,
+    },
+    Key {
+        name: "color",
+        env: "ONELINERS_COLOR",
+        default: Some("auto"),
+        kind: Kind::Choice(&["auto", "always", "never"]),
+        project: true,
+    },
+    Key {
+        name: "search",
+        env: "ONELINERS_SEARCH",
+        default: Some("fuzzy"),
+        kind: Kind::Choice(&["fuzzy", "exact"]),
+        project: true,
+    },
+    Key {
+        name: "confirm",
+        env: "ONELINERS_CONFIRM",
+        default: Some("destructive"),
+        kind: Kind::Choice(&["destructive", "always", "never"]),
+        project: false,
+    },
+    Key {
+        name: "confirm-delete",
+        env: "ONELINERS_CONFIRM_DELETE",
+        default: Some("always"),
+        kind: Kind::Choice(&["always", "never"]),
+        project: false,
+    },
+];
+
+pub fn key(name: &str) -> Option<&'static Key> {
+    KEYS.iter().find(|key| key.name == name)
+}
+
+impl Key {
+    /// Checks `value` and returns the reason it isn't allowed.
+    pub fn check(&self, value: &str) -> std::result::Result<(), String> {
+        match self.kind {
+            Kind::Text if value.trim().is_empty() => Err("expected a non-empty value".to_string()),
+            Kind::Count if value.parse::<usize>().map_or(true, |n| n == 0) => {
+                Err(format!("expected a positive number, not '{}'", value))
+            }
+            Kind::Choice(choices) if !choices.contains(&value) => {
+                Err(format!("expected one of {}, not '{}'", choices.join(", "), value))
+            }
+            Kind::Clipboard if value != "auto" && !clipboard::BACKENDS.contains(&value) => {
+                Err(format!("expected auto or one of {}, not '{}'", clipboard::BACKENDS.join(", "), value))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn to_toml(&self, value: &str) -> toml::Value {
+        match (self.kind, value.parse::<i64>()) {
+            (Kind::Count, Ok(n)) => toml::Value::Integer(n),
+            _ => toml::Value::String(value.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    System,
+    User,
+    Project,
+}
+
+impl Scope {
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::System => "system",
+            Scope::User => "user",
+            Scope::Project => "project",
+        }
+    }
+}
+
+/// Where a setting's value came from. Later layers win: defaults, then the
+/// system, user and project files, then the environment, then flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(Scope, String),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(scope, path) => write!(f, "{}:{}", scope.name(), path),
+            Source::Env(var) => write!(f, "env:{}", var),
+            Source::Flag(flag) => write!(f, "flag:{}", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Setting {
+    pub value: String,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    pub fn enabled(self) -> bool {
+        match self {
+            Color::Auto => search::use_color(),
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+
+    /// Like `enabled`, for the picker: it draws on the controlling
+    /// terminal, which is a terminal even when stdout is redirected.
+    pub fn enabled_on_tty(self) -> bool {
+        match self {
+            Color::Auto => std::env::var_os("NO_COLOR").is_none(),
+            color => color.enabled(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Which snippets `run` asks about before running them. Deletes have their
+/// own setting, `confirm-delete`.
+pub enum Confirm {
+    /// Destructive snippets only.
+    Destructive,
+    Always,
+    Never,
+}
+
+/// The settings in effect, with where each came from.
+#[derive(Debug)]
+pub struct Config {
+    settings: BTreeMap<&'static str, Setting>,
+    /// What was wrong with the config files and environment. Each names
+    /// the file or variable and the setting, which was left out.
+    warnings: Vec<Error>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let settings = KEYS
+            .iter()
+            .filter_map(|key| Some((key.name, Setting { value: key.default?.to_string(), source: Source::Default })))
+            .collect();
+        Config { settings, warnings: Vec::new() }
+    }
+}
+
+/// The nearest project file, looking in the current directory and then
+/// its parents.
+pub fn project_file() -> Option<String> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+        .and_then(|path| path.into_os_string().into_string().ok())
+}
+
+/// The file `config set` changes for `scope`. A project file is created in
+/// the current directory when there is none yet.
+pub fn scope_file(scope: Scope) -> Result<String> {
+    match scope {
+        Scope::System => Ok(SYSTEM_FILE.to_string()),
+        Scope::User => paths::config_file(),
+        Scope::Project => match project_file() {
+            Some(path) => Ok(path),
+            None => Ok(PROJECT_FILE.to_string()),
+        },
+    }
+}
+
+fn read_file(path: &str) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::io("read", path, e)),
+    }
+}
+
+fn line_of(contents: &str, offset: usize) -> usize {
+    contents[..offset].matches('\n').count() + 1
+}
+
+impl Config {
+    /// Reads every layer but the command-line flags, which the caller adds
+    /// with `set_flag`. A bad entry in a file or the environment doesn't
+    /// stop the tool, or a cloned repo's project file could make it
+    /// unusable there; it is left out and reported in `warnings`.
+    pub fn load() -> Result<Config> {
+        let mut config = Config::default();
+        config.load_file(Scope::System, SYSTEM_FILE);
+        config.load_file(Scope::User, &paths::config_file()?);
+        if let Some(path) = project_file() {
+            config.load_file(Scope::Project, &path);
+        }
+        config.load_env(|var| env::var(var).ok());
+        Ok(config)
+    }
+
+    /// Applies the settings in `path`, if it exists. A file that can't be
+    /// read or parsed is skipped as a whole, a bad entry on its own.
+    pub fn load_file(&mut self, scope: Scope, path: &str) {
+        let contents = match read_file(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => return,
+            Err(e) => return self.warnings.push(e),
+        };
+        let table: BTreeMap<String, toml::Spanned<toml::Value>> = match toml::from_str(&contents) {
+            Ok(table) => table,
+            Err(e) => {
+                let line = e.span().map(|span| line_of(&contents, span.start));
+                return self.warnings.push(Error::parse(path, line, e.message()));
+            }
+        };
+
+        for (name, value) in table {
+            let line = Some(line_of(&contents, value.span().start));
+            match Config::file_setting(scope, &name, value.into_inner()) {
+                Ok((key, value)) => {
+                    self.settings.insert(key.name, Setting { value, source: Source::File(scope, path.to_string()) });
+                }
+                Err(message) => self.warnings.push(Error::parse(path, line, message)),
+            }
+        }
+    }
+
+    /// Checks one entry of a config file.
+    fn file_setting(scope: Scope, name: &str, value: toml::Value) -> std::result::Result<(&'static Key, String), String> {
+        let key = key(name).ok_or_else(|| format!("unknown setting '{}'", name))?;
+        if scope == Scope::Project && !key.project {
+            return Err(format!("'{}' can only be set in the user or system config", name));
+        }
+        let value = match value {
+            toml::Value::String(value) => value,
+            toml::Value::Integer(n) => n.to_string(),
+            other => return Err(format!("'{}' can't be a {}", name, other.type_str())),
+        };
+        key.check(&value).map_err(|message| format!("{}: {}", name, message))?;
+        Ok((key, value))
+    }
+
+    /// Applies the environment variables that `var` looks up.
+    fn load_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        for key in KEYS {
+            let Some(value) = var(key.env).filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            match key.check(&value) {
+                Ok(()) => {
+                    self.settings.insert(key.name, Setting { value, source: Source::Env(key.env) });
+                }
+                Err(message) => self.warnings.push(Error::Invalid(format!("{}: {}", key.env, message))),
+            }
+        }
+    }
+
+    /// What was left out while loading; see `load`.
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Overrides `name` with the value of command-line flag `flag`, when
+    /// given. Unlike the other layers, a bad flag is an error: it was typed
+    /// just now.
+    pub fn set_flag(&mut self, name: &str, flag: &'static str, value: Option<String>) -> Result<()> {
+        if let (Some(key), Some(value)) = (key(name), value) {
+            key.check(&value).map_err(|message| Error::Invalid(format!("{}: {}", flag, message)))?;
+            self.settings.insert(key.name, Setting { value, source: Source::Flag(flag) });
+        }
+        Ok(())
+    }
+
+    pub fn setting(&self, name: &str) -> Option<&Setting> {
+        self.settings.get(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.setting(name).map(|setting| setting.value.as_str())
+    }
+
+    /// Every setting that has a value, in the order of `KEYS`.
+    pub fn list(&self) -> Vec<(&'static Key, &Setting)> {
+        KEYS.iter().filter_map(|key| Some((key, self.settings.get(key.name)?))).collect()
+    }
+
+    /// The configured store. A relative path from a config file is taken
+    /// relative to that file's directory.
+    pub fn store(&self) -> Result<Option<String>> {
+        let Some(setting) = self.setting("store") else {
+            return Ok(None);
+        };
+        let path = paths::expand_home(&setting.value)?;
+        match &setting.source {
+            Source::File(_, file) if Path::new(&path).is_relative() => {
+                let dir = Path::new(file).parent().map(Path::to_path_buf).unwrap_or_default();
+                let joined: PathBuf = dir.join(&path);
+                Ok(Some(joined.to_string_lossy().into_owned()))
+            }
+            _ => Ok(Some(path)),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.get("limit").and_then(|value| value.parse().ok()).unwrap_or(10)
+    }
+
+    pub fn clipboard(&self) -> Option<&str> {
+        self.get("clipboard")
+    }
+
+    pub fn shell(&self) -> Option<&str> {
+        self.get("shell")
+    }
+
+    pub fn color(&self) -> Color {
+        match self.get("color") {
+            Some("always") => Color::Always,
+            Some("never") => Color::Never,
+            _ => Color::Auto,
+        }
+    }
+
+    pub fn search(&self) -> Mode {
+        match self.get("search") {
+            Some("exact") => Mode::Exact,
+            _ => Mode::Fuzzy,
+        }
+    }
+
+    pub fn confirm(&self) -> Confirm {
+        match self.get("confirm") {
+            Some("always") => Confirm::Always,
+            Some("never") => Confirm::Never,
+            _ => Confirm::Destructive,
+        }
+    }
+
+    /// Whether `delete` lists what it is about to delete and asks first.
+    pub fn confirm_delete(&self) -> bool {
+        self.get("confirm-delete") != Some("never")
+    }
+}
+
+/// Sets `name` to `value` in the config file at `path`, or removes it when
+/// `value` is `None`. Other settings in the file are kept, comments are not.
+///
+/// Removing is allowed for any name, so `config unset` can clean up a file
+/// that holds an unknown setting or one its scope may not set.
+pub fn write_setting(path: &str, scope: Scope, name: &str, value: Option<&str>) -> Result<()> {
+    let contents = read_file(path)?.unwrap_or_default();
+    let mut table: toml::Table = toml::from_str(&contents)
+        .map_err(|e| Error::parse(path, e.span().map(|span| line_of(&contents, span.start)), e.message()))?;
+    match value {
+        Some(value) => {
+            let key = key(name).ok_or_else(|| Error::Invalid(format!("unknown setting '{}'", name)))?;
+            if scope == Scope::Project && !key.project {
+                return Err(Error::Invalid(format!("'{}' can only be set in the user or system config", name)));
+            }
+            key.check(value).map_err(|message| Error::Invalid(format!("{}: {}", name, message)))?;
+            table.insert(key.name.to_string(), key.to_toml(value));
+        }
+        None => {
+            table.remove(name);
+        }
+    }
+    let out = toml::to_string(&table).map_err(|e| Error::Invalid(e.to_string()))?;
+    store::write_atomic(path, out.as_bytes()).context("write", path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("oneliners-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, contents: &str) -> String {
+        let path = dir.join(name).to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn origin(config: &Config, name: &str) -> String {
+        let setting = config.setting(name).unwrap();
+        format!("{}\t{}", setting.source, setting.value)
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let dir = scratch_dir("layers");
+        let system = write(&dir, "system.toml", "limit = 1\ncolor = \"never\"\nsearch = \"exact\"\nconfirm = \"always\"\n");
+        let user = write(&dir, "user.toml", "limit = 2\ncolor = \"always\"\nsearch = \"fuzzy\"\n");
+        let project = write(&dir, "project.toml", "limit = 3\ncolor = \"auto\"\n");
+
+        let mut config = Config::default();
+        assert_eq!(origin(&config, "limit"), "default\t10");
+        config.load_file(Scope::System, &system);
+        assert_eq!(origin(&config, "limit"), format!("system:{}\t1", system));
+        config.load_file(Scope::User, &user);
+        assert_eq!(origin(&config, "limit"), format!("user:{}\t2", user));
+        config.load_file(Scope::Project, &project);
+        assert_eq!(origin(&config, "limit"), format!("project:{}\t3", project));
+        config.load_env(|var| (var == "ONELINERS_LIMIT").then(|| "4".to_string()));
+        assert_eq!(origin(&config, "limit"), "env:ONELINERS_LIMIT\t4");
+        config.set_flag("limit", "--limit", Some("5".to_string())).unwrap();
+        assert_eq!(origin(&config, "limit"), "flag:--limit\t5");
+
+        // Each setting keeps the last layer that set it.
+        assert_eq!(origin(&config, "color"), format!("project:{}\tauto", project));
+        assert_eq!(origin(&config, "search"), format!("user:{}\tfuzzy", user));
+        assert_eq!(origin(&config, "confirm"), format!("system:{}\talways", system));
+        assert_eq!(origin(&config, "clipboard"), "default\tauto");
+        assert!(config.setting("shell").is_none());
+        assert!(config.warnings().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_entries_are_skipped_with_a_warning() {
+        let dir = scratch_dir("bad-entries");
+        let project = write(&dir, "project.toml", "store = \"elsewhere\"\nlimit = 3\nbogus = 1\ncolor = true\n");
+
+        let mut config = Config::default();
+        config.load_file(Scope::Project, &project);
+        config.load_env(|var| (var == "ONELINERS_LIMIT").then(|| "abc".to_string()));
+
+        assert!(config.setting("store").is_none());
+        assert_eq!(config.limit(), 3);
+        assert_eq!(config.color(), Color::Auto);
+        let warnings: Vec<String> = config.warnings().iter().map(Error::to_string).collect();
+        assert_eq!(
+            warnings,
+            [
+                format!("{}, line 3: unknown setting 'bogus'", project),
+                format!("{}, line 4: 'color' can't be a boolean", project),
+                format!("{}, line 1: 'store' can only be set in the user or system config", project),
+                "ONELINERS_LIMIT: expected a positive number, not 'abc'".to_string(),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_files_are_skipped_with_a_warning() {
+        let dir = scratch_dir("unreadable");
+        let broken = write(&dir, "broken.toml", "limit = 2\nlimit = [\n");
+
+        let mut config = Config::default();
+        config.load_file(Scope::User, &broken);
+        config.load_file(Scope::User, dir.join("missing.toml").to_str().unwrap());
+
+        assert_eq!(config.limit(), 10);
+        assert_eq!(config.warnings().len(), 1);
+        assert!(matches!(&config.warnings()[0], Error::Parse { path, .. } if *path == broken));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_flags_are_errors() {
+        let mut config = Config::default();
+        let error = config.set_flag("color", "--color", Some("sometimes".to_string())).unwrap_err();
+        assert_eq!(error.to_string(), "--color: expected one of auto, always, never, not 'sometimes'");
+        config.set_flag("color", "--color", None).unwrap();
+        assert_eq!(origin(&config, "color"), "default\tauto");
+    }
+
+    #[test]
+    fn unset_removes_settings_a_scope_may_not_set() {
+        let dir = scratch_dir("unset");
+        let project = write(&dir, "project.toml", "store = \"elsewhere\"\nbogus = 1\nlimit = 3\n");
+
+        let error = write_setting(&project, Scope::Project, "store", Some("other")).unwrap_err();
+        assert_eq!(error.to_string(), "'store' can only be set in the user or system config");
+        write_setting(&project, Scope::Project, "store", None).unwrap();
+        write_setting(&project, Scope::Project, "bogus", None).unwrap();
+        assert_eq!(fs::read_to_string(&project).unwrap(), "limit = 3\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use crate::snippet::{self, Draft};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn editor_command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Creates a fresh file in the temporary directory that only the user can
+/// read. It must not exist yet, so a file or symlink planted under a
+/// guessed name is never written through.
+fn create_temp_file() -> io::Result<(PathBuf, File)> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mut attempt = 0;
+    loop {
+        let name = format!("oneliners-{}-{:08x}-{}.txt", std::process::id(), nanos, attempt);
+        let path = env::temp_dir().join(name);
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Opens `$VISUAL`/`$EDITOR` on a temporary file holding `initial` and
+/// returns what the user saved.
+pub fn edit_text(initial: &str) -> io::Result<String> {
+    let (path, mut file) = create_temp_file()?;
+    if let Err(e) = file.write_all(initial.as_bytes()) {
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    drop(file);
+
+    // The editor setting may carry arguments (`code --wait`), so let the
+    // shell split it.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor_command()))
+        .arg("sh")
+        .arg(&path)
+        .status();
+
+    let result = match status {
+        Ok(status) if status.success() => fs::read_to_string(&path),
+        Ok(status) => Err(io::Error::other(format!("editor exited with {}", status))),
+        Err(e) => Err(e),
+    };
+    let _ = fs::remove_file(&path);
+    result
+}
+
+const SEPARATOR: &str = "---";
+
+const HELP: &str = "\
+# Fill in the fields and put the command below the --- line; it may span
+# several lines. Lines starting with # above the --- line are ignored.
+# Tags are separated by commas or spaces. Save an empty command to abort.
+";
+
+pub fn render_template(draft: &Draft) -> String {
+    let mut out = String::from(HELP);
+    out.push_str(&format!("description: {}\n", draft.description.as_deref().unwrap_or("")));
+    out.push_str(&format!("tags: {}\n", draft.tags.join(", ")));
+    out.push_str(SEPARATOR);
+    out.push('\n');
+    out.push_str(&draft.command);
+    out.push('\n');
+    out
+}
+
+/// Reads a template back. On failure, returns every problem found so they
+/// can all be shown at once.
+pub fn parse_template(text: &str) -> Result<Draft, Vec<String>> {
+    let mut draft = Draft::default();
+    let mut errors = Vec::new();
+
+    let Some((header, command)) = text.split_once(&format!("\n{}\n", SEPARATOR)).or_else(|| {
+        text.strip_prefix(&format!("{}\n", SEPARATOR)).map(|command| ("", command))
+    }) else {
+        return Err(vec![format!("the '{}' line before the command is missing", SEPARATOR)]);
+    };
+
+    for (i, line) in header.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some(("description", value)) => {
+                let value = value.trim();
+                draft.description = (!value.is_empty()).then(|| value.to_string());
+            }
+            Some(("tags", value)) => {
+                let tags: Vec<&str> = value.split([',', ' ']).filter(|t| !t.trim().is_empty()).collect();
+                for tag in &tags {
+                    if !snippet::is_valid_tag(tag.trim()) {
+                        errors.push(format!("invalid tag '{}': use letters, digits and - _ . : /", tag.trim()));
+                    }
+                }
+                draft.tags = snippet::normalize_tags(&tags);
+            }
+            Some((key, _)) => errors.push(format!("line {}: unknown field '{}'", i + 1, key.trim())),
+            None => errors.push(format!("line {}: expected 'field: value', got '{}'", i + 1, line)),
+        }
+    }
+
+    draft.command = snippet::normalize_command(command);
+    if errors.is_empty() { Ok(draft) } else { Err(errors) }
+}
+
+/// Lets the user edit `draft` in their editor, reopening it with the
+/// errors listed at the top until it parses. Returns `None` if the command
+/// is left empty.
+pub fn edit_draft(draft: &Draft) -> io::Result<Option<Draft>> {
+    let mut text = render_template(draft);
+    loop {
+        let edited = edit_text(&text)?;
+        match parse_template(&edited) {
+            Ok(draft) if draft.command.is_empty() => return Ok(None),
+            Ok(draft) => return Ok(Some(draft)),
+            Err(errors) => {
+                // Keep the user's edits, minus the errors from last time.
+                let kept: String = edited
+                    .lines()
+                    .skip_while(|line| line.starts_with("# ERROR: ") || *line == "#")
+                    .map(|line| format!("{}\n", line))
+                    .collect();
+                text = errors.iter().map(|e| format!("# ERROR: {}\n", e)).collect::<String>() + "#\n" + &kept;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn temp_files_are_private_and_never_shared() {
+        let (first, _) = create_temp_file().unwrap();
+        let (second, _) = create_temp_file().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        let _ = fs::remove_file(&first);
+        let _ = fs::remove_file(&second);
+    }
+
+    #[test]
+    fn templates_round_trip() {
+        let draft = Draft {
+            command: "for f in *; do\n  echo \"$f\"\ndone".to_string(),
+            description: Some("list files".to_string()),
+            tags: vec!["files".to_string(), "loop".to_string()],
+        };
+        assert_eq!(parse_template(&render_template(&draft)), Ok(draft));
+    }
+
+    #[test]
+    fn template_problems_are_reported_together() {
+        let errors = parse_template("tags: ok bad!\nsize: 3\nnonsense\n---\necho hi\n").unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("invalid tag 'bad!'"));
+        assert_eq!(errors[1], "line 2: unknown field 'size'");
+        assert_eq!(errors[2], "line 3: expected 'field: value', got 'nonsense'");
+        assert!(parse_template("echo hi\n").is_err());
+    }
+}
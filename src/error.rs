@@ -0,0 +1,147 @@
+use crate::snippet;
+use std::fmt;
+use std::io;
+
+/// Exit statuses of the command-line tool, one per class of failure. Usage
+/// errors exit with 2, as reported by clap.
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+pub const EXIT_PARSE: i32 = 5;
+pub const EXIT_PERMISSION: i32 = 6;
+pub const EXIT_AMBIGUOUS: i32 = 7;
+/// The user cancelled a picker or declined a confirmation, like Ctrl-C.
+pub const EXIT_ABORTED: i32 = 130;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing `path` failed; `action` says what was being
+    /// done, such as `read` or `lock`.
+    Io { action: &'static str, path: String, source: io::Error },
+    /// `path` holds something this build can't understand. `line` counts
+    /// from 1.
+    Parse { path: String, line: Option<usize>, message: String },
+    /// No snippet has this id.
+    NotFound(u64),
+    /// Nothing matches this search.
+    NoMatches(String),
+    /// The store holds no snippets at all.
+    EmptyStore,
+    /// A search meant to name one snippet matches several; `candidates`
+    /// are the best of them, as id and command.
+    Ambiguous { query: String, candidates: Vec<(u64, String)> },
+    /// The operation log has nothing on this snippet id.
+    NoHistory(u64),
+    /// A placeholder generator failed or ran out of time.
+    Generator { command: String, message: String },
+    /// The command is already stored under this id.
+    Duplicate(u64),
+    /// Input that can't be stored, such as an empty command.
+    Invalid(String),
+    /// There is no home directory to keep the store in.
+    NoHome,
+    /// The user cancelled.
+    Aborted,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(action: &'static str, path: impl Into<String>, source: io::Error) -> Error {
+        Error::Io { action, path: path.into(), source }
+    }
+
+    pub fn parse(path: impl Into<String>, line: Option<usize>, message: impl Into<String>) -> Error {
+        Error::Parse { path: path.into(), line, message: message.into() }
+    }
+
+    /// The exit status the command-line tool reports this error with.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io { source, .. } if source.kind() == io::ErrorKind::PermissionDenied => EXIT_PERMISSION,
+            Error::Io { .. } => EXIT_IO,
+            Error::Parse { .. } => EXIT_PARSE,
+            Error::NotFound(_) | Error::NoMatches(_) | Error::EmptyStore | Error::NoHistory(_) => EXIT_NOT_FOUND,
+            Error::Ambiguous { .. } => EXIT_AMBIGUOUS,
+            Error::Aborted => EXIT_ABORTED,
+            Error::Generator { .. } | Error::Duplicate(_) | Error::Invalid(_) | Error::NoHome => EXIT_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { action, path, source } => write!(f, "failed to {} {}: {}", action, path, source),
+            Error::Parse { path, line: Some(line), message } => write!(f, "{}, line {}: {}", path, line, message),
+            Error::Parse { path, line: None, message } => write!(f, "{}: {}", path, message),
+            Error::NotFound(id) => write!(f, "no snippet with id {}", id),
+            Error::NoMatches(query) => write!(f, "no snippet matches '{}'", query),
+            Error::EmptyStore => write!(f, "no oneliners stored yet"),
+            Error::Ambiguous { query, candidates } => {
+                write!(f, "'{}' matches several snippets; pass an id instead:", query)?;
+                for (id, command) in candidates {
+                    let prefix = format!("  {}: ", id);
+                    write!(f, "\n{}{}", prefix, snippet::indent_continuation(command, prefix.len()))?;
+                }
+                Ok(())
+            }
+            Error::NoHistory(id) => write!(f, "no history for snippet {}", id),
+            Error::Generator { command, message } => write!(f, "{} failed: {}", command, message),
+            Error::Duplicate(id) => write!(f, "already stored as snippet {}", id),
+            Error::Invalid(message) => write!(f, "{}", message),
+            Error::NoHome => write!(f, "unable to locate the home directory for the store"),
+            Error::Aborted => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Attaches what was being done, and to which file, to an I/O error.
+pub trait Context<T> {
+    fn context(self, action: &'static str, path: &str) -> Result<T>;
+}
+
+impl<T, E: Into<io::Error>> Context<T> for std::result::Result<T, E> {
+    fn context(self, action: &'static str, path: &str) -> Result<T> {
+        self.map_err(|e| Error::io(action, path, e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_error_has_its_exit_code() {
+        let io_error = |kind| Error::io("read", "store", io::Error::from(kind));
+        let cases = [
+            (Error::Generator { command: "ls".to_string(), message: "timed out".to_string() }, EXIT_FAILURE),
+            (Error::Duplicate(1), EXIT_FAILURE),
+            (Error::Invalid("empty command".to_string()), EXIT_FAILURE),
+            (Error::NoHome, EXIT_FAILURE),
+            (Error::NotFound(1), EXIT_NOT_FOUND),
+            (Error::NoMatches("ls".to_string()), EXIT_NOT_FOUND),
+            (Error::EmptyStore, EXIT_NOT_FOUND),
+            (Error::NoHistory(1), EXIT_NOT_FOUND),
+            (io_error(io::ErrorKind::NotFound), EXIT_IO),
+            (io_error(io::ErrorKind::Other), EXIT_IO),
+            (Error::parse("store", Some(3), "invalid UTF-8"), EXIT_PARSE),
+            (io_error(io::ErrorKind::PermissionDenied), EXIT_PERMISSION),
+            (Error::Ambiguous { query: "ls".to_string(), candidates: vec![(1, "ls".to_string())] }, EXIT_AMBIGUOUS),
+            (Error::Aborted, EXIT_ABORTED),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", error);
+        }
+        assert_eq!((EXIT_FAILURE, EXIT_NOT_FOUND, EXIT_IO, EXIT_PARSE, EXIT_PERMISSION, EXIT_AMBIGUOUS, EXIT_ABORTED), (1, 3, 4, 5, 6, 7, 130));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{Context, Error, Result};
+use crate::snippet;
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a generator may run before it is killed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a placeholder generator through `sh` and returns its non-empty
+/// output lines. The command is killed if it outlives `timeout`, and so is
+/// anything it left running in the background that still holds its output
+/// open.
+pub fn run(command: &str, timeout: Duration) -> Result<Vec<String>> {
+    let failed = |message: String| Error::Generator { command: command.to_string(), message };
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .context("start", "sh")?;
+    // The generator leads its own process group, so killing the group takes
+    // pipelines and background jobs inside it down too.
+    let group = child.id() as i32;
+    let kill_group = || unsafe {
+        libc::kill(-group, libc::SIGKILL);
+    };
+
+    // Drain both pipes on threads so a chatty command can't block on a full
+    // pipe while we wait for it.
+    let out_reader = drain(child.stdout.take().expect("stdout is piped"));
+    let err_reader = drain(child.stderr.take().expect("stderr is piped"));
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = || failed(format!("timed out after {}s", timeout.as_secs_f32()));
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                kill_group();
+                let _ = child.wait();
+                return Err(timed_out());
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(Error::io("wait for", "sh", e)),
+        }
+    };
+
+    // The output is complete once nothing holds the pipes open any more,
+    // which a background job can put off past the deadline.
+    let mut collected = Vec::new();
+    for reader in [out_reader, err_reader] {
+        match reader.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(buf) => collected.push(buf),
+            Err(_) => {
+                kill_group();
+                return Err(timed_out());
+            }
+        }
+    }
+    let (out, err) = (&collected[0], &collected[1]);
+    if !status.success() {
+        let message = String::from_utf8_lossy(err).trim().to_string();
+        return Err(failed(if message.is_empty() { format!("exited with {}", status) } else { message }));
+    }
+
+    Ok(String::from_utf8_lossy(out)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Reads `pipe` to the end on a thread and sends back what it read.
+fn drain(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+    receiver
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Entry {
+    at: u64,
+    lines: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    session: i32,
+    entries: HashMap<String, Entry>,
+}
+
+/// Generator output cached per terminal session, so picking several
+/// snippets in a row doesn't re-run slow commands. Switching to another
+/// session starts from an empty cache.
+pub struct Cache {
+    path: String,
+    file: CacheFile,
+    refresh: bool,
+    ran: HashSet<String>,
+}
+
+fn session_id() -> i32 {
+    unsafe { libc::getsid(0) }
+}
+
+impl Cache {
+    /// With `refresh`, cached output is ignored and overwritten.
+    pub fn load(path: &str, refresh: bool) -> Cache {
+        let session = session_id();
+        let file = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CacheFile>(&contents).ok())
+            .filter(|file| file.session == session)
+            .unwrap_or(CacheFile { session, entries: HashMap::new() });
+        Cache { path: path.to_string(), file, refresh, ran: HashSet::new() }
+    }
+
+    /// Whether `lines` would answer from the cache instead of running
+    /// `command`.
+    pub fn is_cached(&self, command: &str) -> bool {
+        (!self.refresh || self.ran.contains(command)) && self.file.entries.contains_key(command)
+    }
+
+    /// Cached output for `command`, running it on a miss.
+    pub fn lines(&mut self, command: &str, timeout: Duration) -> Result<Vec<String>> {
+        if self.is_cached(command)
+            && let Some(entry) = self.file.entries.get(command)
+        {
+            return Ok(entry.lines.clone());
+        }
+        let lines = run(command, timeout)?;
+        self.ran.insert(command.to_string());
+        self.file.entries.insert(command.to_string(), Entry { at: snippet::now(), lines: lines.clone() });
+        Ok(lines)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        store::write_atomic(&self.path, serde_json::to_string(&self.file)?.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_the_non_empty_output_lines() {
+        assert_eq!(run("printf ' a \\n\\nb\\n'", DEFAULT_TIMEOUT).unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn reports_failures_with_their_stderr() {
+        let message = run("echo oops >&2; exit 3", DEFAULT_TIMEOUT).unwrap_err().to_string();
+        assert_eq!(message, "echo oops >&2; exit 3 failed: oops");
+        let message = run("exit 3", DEFAULT_TIMEOUT).unwrap_err().to_string();
+        assert_eq!(message, "exit 3 failed: exited with exit status: 3");
+    }
+
+    #[test]
+    fn kills_slow_commands() {
+        let started = Instant::now();
+        let error = run("sleep 5; echo late", Duration::from_millis(300)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(error.to_string().ends_with("timed out after 0.3s"), "{}", error);
+    }
+
+    #[test]
+    fn background_jobs_holding_the_output_do_not_outlast_the_timeout() {
+        let started = Instant::now();
+        let error = run("sleep 5 & echo hi", Duration::from_millis(300)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(error.to_string().ends_with("timed out after 0.3s"), "{}", error);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::safety;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Shells whose history files can be read.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    /// The login shell from `$SHELL`, falling back to bash.
+    pub fn detect() -> Shell {
+        env::var("SHELL")
+            .ok()
+            .and_then(|shell| Shell::from_name(shell.rsplit('/').next().unwrap_or_default()))
+            .unwrap_or(Shell::Bash)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// Where the shell keeps its history: `$HISTFILE` when it is exported,
+    /// otherwise the shell's default location.
+    pub fn history_file(&self) -> Option<PathBuf> {
+        let histfile = env::var_os("HISTFILE").filter(|path| !path.is_empty());
+        let home = dirs::home_dir()?;
+        match self {
+            Shell::Bash => Some(histfile.map(PathBuf::from).unwrap_or_else(|| home.join(".bash_history"))),
+            Shell::Zsh => {
+                let zdotdir = env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or(home);
+                Some(histfile.map(PathBuf::from).unwrap_or_else(|| zdotdir.join(".zsh_history")))
+            }
+            Shell::Fish => {
+                let data = dirs::data_dir().unwrap_or_else(|| home.join(".local/share"));
+                let session = env::var("fish_history").unwrap_or_else(|_| "fish".to_string());
+                Some(data.join("fish").join(format!("{}_history", session)))
+            }
+        }
+    }
+}
+
+/// Reads the commands in a history file, oldest first.
+pub fn read(shell: Shell, path: &Path) -> io::Result<Vec<String>> {
+    let bytes = fs::read(path)?;
+    let commands = match shell {
+        Shell::Bash => parse_bash(&String::from_utf8_lossy(&bytes)),
+        Shell::Zsh => parse_zsh(&String::from_utf8_lossy(&unmetafy(&bytes))),
+        Shell::Fish => parse_fish(&String::from_utf8_lossy(&bytes)),
+    };
+    Ok(commands.into_iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+}
+
+fn is_bash_timestamp(line: &str) -> bool {
+    line.strip_prefix('#').is_some_and(|t| !t.is_empty() && t.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// One command per line, unless `HISTTIMEFORMAT` was set: then every
+/// command follows a `#<unix time>` line and may span several lines.
+fn parse_bash(contents: &str) -> Vec<String> {
+    if !contents.lines().any(is_bash_timestamp) {
+        return contents.lines().map(str::to_string).collect();
+    }
+
+    let mut commands: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+    for line in contents.lines() {
+        if is_bash_timestamp(line) {
+            commands.extend(current.take());
+            current = Some(String::new());
+        } else {
+            match &mut current {
+                Some(command) if !command.is_empty() => {
+                    command.push('\n');
+                    command.push_str(line);
+                }
+                Some(command) => command.push_str(line),
+                None => commands.push(line.to_string()),
+            }
+        }
+    }
+    commands.extend(current);
+    commands
+}
+
+/// zsh escapes some bytes in its history ("metafied"): 0x83 is followed by
+/// the real byte xor 0x20.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        if b == 0x83 {
+            if let Some(&next) = iter.next() {
+                out.push(next ^ 0x20);
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Plain lines, or `: <start>:<elapsed>;<command>` with EXTENDED_HISTORY.
+/// Multi-line commands end every line but the last with a backslash.
+fn parse_zsh(contents: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current: Option<String> = None;
+    for line in contents.lines() {
+        let text = match current.take() {
+            Some(mut command) => {
+                command.push('\n');
+                command.push_str(line);
+                command
+            }
+            None => match line.strip_prefix(": ").and_then(|rest| rest.split_once(';')) {
+                Some((_, command)) => command.to_string(),
+                None => line.to_string(),
+            },
+        };
+        match text.strip_suffix('\\') {
+            Some(continued) => current = Some(continued.to_string()),
+            None => commands.push(text),
+        }
+    }
+    commands.extend(current);
+    commands
+}
+
+/// fish writes a YAML-like list of `- cmd: <command>` entries with `\n` and
+/// `\\` escaped.
+fn parse_fish(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(|command| {
+            let mut out = String::with_capacity(command.len());
+            let mut chars = command.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => {
+                        out.push('\n');
+                        chars.next();
+                    }
+                    ('\\', Some('\\')) => {
+                        out.push('\\');
+                        chars.next();
+                    }
+                    _ => out.push(c),
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// Whether `command` runs oneliners itself, like the `oneliners store
+/// --last` that is being handled right now.
+pub fn is_self_invocation(command: &str) -> bool {
+    let exe = env::current_exe().ok().and_then(|path| path.file_name()?.to_str().map(str::to_string));
+    let first = command
+        .split_whitespace()
+        .find(|word| !word.contains('='))
+        .map(|word| word.rsplit('/').next().unwrap_or(word));
+    first.is_some_and(|first| first == "oneliners" || exe.as_deref() == Some(first))
+}
+
+/// The most recent commands first, without duplicates or oneliners calls.
+pub fn recent(commands: &[String], limit: usize) -> Vec<String> {
+    let mut recent: Vec<String> = Vec::new();
+    for command in commands.iter().rev() {
+        if recent.len() == limit {
+            break;
+        }
+        if !is_self_invocation(command) && !recent.contains(command) {
+            recent.push(command.clone());
+        }
+    }
+    recent
+}
+
+/// Programs that aren't worth a snippet when run on their own.
+const TRIVIAL: &[&str] = &[
+    "ls", "ll", "la", "l", "cd", "pwd", "clear", "exit", "logout", "history", "fg", "bg", "jobs", "which", "man",
+    "echo", "cat", "less", "vi", "vim", "nvim", "nano", "top", "htop", "whoami", "date", "reset", "source", ".",
+];
+
+/// A simple command whose program is in `TRIVIAL`, or anything shorter than
+/// a handful of characters.
+pub fn is_trivial(command: &str) -> bool {
+    if command.chars().count() < 4 {
+        return true;
+    }
+    match safety::split_commands(command).as_slice() {
+        [words] => words.first().is_some_and(|program| TRIVIAL.contains(&program.as_str())),
+        _ => false,
+    }
+}
+
+/// A history command worth offering for import.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub command: String,
+    /// How often the command appears in the history.
+    pub count: usize,
+    pub score: f64,
+}
+
+/// Deduplicates `commands` (oldest first), drops trivial ones and calls to
+/// oneliners, and ranks the rest: commands run often and long enough to be
+/// tedious to retype come first, then the more recently used.
+pub fn candidates(commands: &[String]) -> Vec<Candidate> {
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, command) in commands.iter().enumerate() {
+        let entry = counts.entry(command.as_str()).or_insert((0, i));
+        entry.0 += 1;
+        entry.1 = i;
+    }
+
+    let mut ranked: Vec<(Candidate, usize)> = counts
+        .into_iter()
+        .filter(|(command, _)| !is_trivial(command) && !is_self_invocation(command))
+        .map(|(command, (count, last))| {
+            let length = command.chars().count().min(120) as f64;
+            let score = count as f64 * length.sqrt();
+            (Candidate { command: command.to_string(), count, score }, last)
+        })
+        .collect();
+    ranked.sort_by(|(a, a_last), (b, b_last)| b.score.total_cmp(&a.score).then(b_last.cmp(a_last)));
+    ranked.into_iter().map(|(candidate, _)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bash_plain_lines() {
+        assert_eq!(parse_bash("ls -la\ngit status\n\n"), ["ls -la", "git status", ""]);
+    }
+
+    #[test]
+    fn bash_with_timestamps_keeps_multi_line_commands() {
+        let contents = "echo before\n#1700000000\ngit status\n#1700000005\nfor f in *; do\n  echo \"$f\"\ndone\n#1700000010\n#not-a-time\n";
+        assert_eq!(parse_bash(contents), ["echo before", "git status", "for f in *; do\n  echo \"$f\"\ndone", "#not-a-time"]);
+    }
+
+    #[test]
+    fn zsh_extended_history() {
+        let contents = ": 1700000000:0;git status\n: 1700000003:12;for f in *; do\\\n  echo $f\\\ndone\nplain line\n: 1700000009:0;echo a;b\n";
+        assert_eq!(parse_zsh(contents), ["git status", "for f in *; do\n  echo $f\ndone", "plain line", "echo a;b"]);
+        // A continuation at the very end is kept rather than dropped.
+        assert_eq!(parse_zsh(": 1:0;echo \\\n"), ["echo "]);
+    }
+
+    #[test]
+    fn zsh_metafied_bytes() {
+        // 'œ' is C5 93, and zsh writes 0x93 as 0x83 0xB3.
+        assert_eq!(unmetafy(b"echo \xc5\x83\xb3"), "echo œ".as_bytes());
+        assert_eq!(unmetafy(b"plain"), b"plain");
+        assert_eq!(unmetafy(b"cut\x83"), b"cut");
+    }
+
+    #[test]
+    fn fish_entries_and_escapes() {
+        let contents = "- cmd: git status\n  when: 1700000000\n- cmd: printf 'a\\nb'\\necho \\\\n done\n  when: 1700000001\n  paths:\n    - /tmp\n";
+        assert_eq!(parse_fish(contents), ["git status", "printf 'a\nb'\necho \\n done"]);
+    }
+
+    #[test]
+    fn self_invocations() {
+        assert!(is_self_invocation("oneliners store --last"));
+        assert!(is_self_invocation("/usr/local/bin/oneliners get docker"));
+        assert!(is_self_invocation("ONELINERS_FILE=/tmp/x oneliners list"));
+        assert!(!is_self_invocation("echo oneliners"));
+        assert!(!is_self_invocation("git log"));
+        assert!(!is_self_invocation(""));
+    }
+
+    #[test]
+    fn recent_skips_duplicates_and_self_invocations() {
+        let commands: Vec<String> =
+            ["git pull", "make test", "git pull", "oneliners store --last"].iter().map(|c| c.to_string()).collect();
+        assert_eq!(recent(&commands, 10), ["git pull", "make test"]);
+        assert_eq!(recent(&commands, 1), ["git pull"]);
+    }
+
+    #[test]
+    fn candidates_drop_trivial_commands_and_rank_by_use() {
+        let commands: Vec<String> = ["ls", "cd /tmp", "docker compose up -d", "kubectl get pods -A", "docker compose up -d", "ls | wc -l"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let found: Vec<(String, usize)> = candidates(&commands).into_iter().map(|c| (c.command, c.count)).collect();
+        assert_eq!(
+            found,
+            [("docker compose up -d".to_string(), 2), ("kubectl get pods -A".to_string(), 1), ("ls | wc -l".to_string(), 1)]
+        );
+    }
+}
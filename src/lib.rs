@@ -0,0 +1,39 @@
+//! Store, search and run shell one-liners.
+//!
+//! The `oneliners` binary is a thin command-line frontend over this crate.
+//! Other tools can read and change the same store through
+//! [`SnippetStore`]:
+//!
+//! ```no_run
+//! use oneliners::{FileStore, Query, SnippetStore};
+//!
+//! let store = FileStore::new("/home/me/.local/share/oneliners/snippets");
+//! for (snippet, _) in store.search(&Query::parse("tag:docker logs"))? {
+//!     println!("{}: {}", snippet.id, snippet.command);
+//! }
+//! # Ok::<(), oneliners::Error>(())
+//! ```
+
+pub mod clipboard;
+pub mod config;
+pub mod editor;
+pub mod error;
+pub mod generator;
+pub mod history;
+pub mod migrate;
+pub mod oplog;
+pub mod output;
+pub mod paths;
+pub mod picker;
+pub mod placeholder;
+pub mod run;
+pub mod safety;
+pub mod search;
+pub mod snippet;
+pub mod store;
+pub mod terminal;
+
+pub use error::{Error, Result};
+pub use search::{Match, Mode, Query};
+pub use snippet::{Draft, Snippet};
+pub use store::{FileStore, SnippetStore};
@@ -0,0 +1,1107 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::exit;
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand};
+use oneliners::{clipboard, editor, generator, history, migrate, output, picker, placeholder, run, safety, search, snippet, terminal};
+use oneliners::error::{Context, EXIT_ABORTED, EXIT_NOT_FOUND};
+use oneliners::config::{self, Config, Confirm, Scope};
+use oneliners::paths::{Origin, Paths};
+use oneliners::store::{Listing, SortKey};
+use oneliners::{Error, FileStore, Query, Snippet, SnippetStore};
+
+#[derive(Parser)]
+#[command(name = "oneliner-cli")]
+#[command(about = "A simple CLI tool to store and retrieve oneliners", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    #[arg(long, global = true, value_name = "BACKEND", help = "Clipboard backend: auto, wl-copy, xclip, xsel, tmux, osc52 or stdout")]
+    clipboard: Option<String>,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Store file to use instead of the default (also ONELINERS_FILE)")]
+    store: Option<String>,
+
+    #[arg(long, global = true, value_name = "WHEN", value_parser = PossibleValuesParser::new(["auto", "always", "never"]), help = "Highlight matches: auto, always or never")]
+    color: Option<String>,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    #[arg(help = "The search term to find oneliners")]
+    search: Option<String>,
+
+    #[arg(long, help = "Match the search term literally instead of fuzzily")]
+    exact: bool,
+
+    #[arg(long = "var", value_name = "NAME=VALUE", help = "Value for a <placeholder>, may be repeated")]
+    vars: Vec<String>,
+
+    #[arg(long, help = "Re-run placeholder generators instead of using this session's cached output")]
+    refresh: bool,
+}
+
+#[derive(Args)]
+struct StoreArgs {
+    #[arg(help = "The oneliner to store, or - to read it from stdin")]
+    oneliner: Option<String>,
+
+    #[arg(long, value_name = "PATH", conflicts_with = "oneliner", help = "Read the oneliner from a file")]
+    file: Option<String>,
+
+    #[arg(long, conflicts_with_all = ["oneliner", "file"], help = "Store the last command from your shell history")]
+    last: bool,
+
+    #[arg(long, conflicts_with_all = ["oneliner", "file", "last"], help = "Pick one of the recent commands from your shell history")]
+    pick: bool,
+
+    #[arg(long, value_name = "SHELL", value_parser = PossibleValuesParser::new(history::SHELLS), help = "Shell whose history --last and --pick read [default: $SHELL]")]
+    shell: Option<String>,
+
+    #[arg(long, help = "Write the oneliner, its description and tags in $EDITOR")]
+    edit: bool,
+
+    #[arg(short, long, value_name = "TEXT", help = "What the oneliner does, in your own words")]
+    description: Option<String>,
+
+    #[arg(short, long = "tag", value_name = "TAG", help = "Tag the oneliner, may be repeated")]
+    tags: Vec<String>,
+
+    #[arg(long, help = "Don't tag the oneliner with its program name when no --tag is given")]
+    no_auto_tag: bool,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    Store {
+        #[command(flatten)]
+        source: StoreArgs,
+    },
+    
+    Get {
+        #[command(flatten)]
+        query: QueryArgs,
+
+        #[arg(short = 'n', long, help = "Maximum number of matches to show [default: 10]")]
+        limit: Option<usize>,
+
+        #[arg(short, long, help = "Write the selected oneliner to stdout instead of the clipboard")]
+        print: bool,
+
+        #[arg(long, value_parser = format_parser(), conflicts_with = "print", help = "Print every match in this format instead of picking one")]
+        format: Option<output::Format>,
+    },
+
+    #[command(about = "Select a oneliner and execute it")]
+    Run {
+        #[command(flatten)]
+        query: QueryArgs,
+
+        #[arg(long, help = "Print the command that would run instead of running it")]
+        dry_run: bool,
+
+        #[arg(long, value_name = "PATH", help = "Shell to run the oneliner with [default: $SHELL]")]
+        shell: Option<String>,
+    },
+
+    List {
+        #[arg(short, long = "tag", value_name = "TAG", help = "Only list oneliners with this tag, may be repeated")]
+        tags: Vec<String>,
+
+        #[arg(short = 'n', long, help = "Show at most this many oneliners")]
+        limit: Option<usize>,
+
+        #[arg(long, default_value_t = 0, help = "Skip this many oneliners first")]
+        offset: usize,
+
+        #[arg(long, value_parser = sort_parser(), default_value = "created", help = "Order to list oneliners in")]
+        sort: SortKey,
+
+        #[arg(short, long, help = "Reverse the order")]
+        reverse: bool,
+
+        #[arg(long, value_parser = format_parser(), help = "Print the oneliners in this format")]
+        format: Option<output::Format>,
+    },
+
+    #[command(about = "List the tags in use and how many oneliners carry each")]
+    Tags,
+
+    #[command(about = "Edit a oneliner in $EDITOR")]
+    Edit {
+        #[arg(help = "Id of the oneliner, or a search term to pick it")]
+        target: String,
+    },
+
+    #[command(about = "Delete oneliners")]
+    Delete {
+        #[arg(required_unless_present = "pattern", help = "Id of the oneliner, or a search term to pick it")]
+        target: Option<String>,
+
+        #[arg(long = "match", value_name = "PATTERN", conflicts_with = "target", help = "Delete every oneliner containing PATTERN")]
+        pattern: Option<String>,
+
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+
+    #[command(about = "Revert the last changes to the store")]
+    Undo {
+        #[arg(default_value_t = 1, help = "How many operations to revert")]
+        count: usize,
+    },
+
+    #[command(about = "Show the past versions of a oneliner")]
+    History {
+        #[arg(help = "Id of the oneliner")]
+        id: u64,
+
+        #[arg(long, value_name = "VERSION", help = "Make this version the current one again")]
+        restore: Option<usize>,
+    },
+
+    #[command(about = "Import oneliners from elsewhere")]
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+
+    #[command(about = "Convert a legacy plain-text store to the record format")]
+    Migrate,
+
+    #[command(about = "Show where the store, config and other files are")]
+    Paths,
+
+    #[command(about = "Show or change settings")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "Print the value of a setting")]
+    Get {
+        key: String,
+
+        #[arg(long, help = "Also print where the value comes from")]
+        show_origin: bool,
+    },
+
+    #[command(about = "Change a setting in a config file")]
+    Set {
+        key: String,
+        value: String,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+
+    #[command(about = "Remove a setting from a config file")]
+    Unset {
+        key: String,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+
+    #[command(about = "Print every setting in effect")]
+    List {
+        #[arg(long, help = "Also print where each value comes from")]
+        show_origin: bool,
+    },
+}
+
+/// Which config file `config set` and `config unset` change; the user's by
+/// default.
+#[derive(Args)]
+struct ScopeArgs {
+    #[arg(long, help = "Change the system-wide config file")]
+    system: bool,
+
+    #[arg(long, conflicts_with = "system", help = "Change the project config file, .oneliners.toml")]
+    project: bool,
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    #[command(about = "Choose commands from your shell history")]
+    History {
+        #[arg(long, value_name = "SHELL", value_parser = PossibleValuesParser::new(history::SHELLS), help = "Shell whose history to read [default: $SHELL]")]
+        shell: Option<String>,
+
+        #[arg(long, value_name = "PATH", help = "History file to read instead of the shell's default")]
+        file: Option<String>,
+
+        #[arg(long, default_value_t = 1, help = "Only offer commands run at least this many times")]
+        min_count: usize,
+
+        #[arg(long, help = "Import every candidate without picking")]
+        all: bool,
+    },
+}
+
+/// Accepts `--format` names, listing each format in `--help`.
+fn format_parser() -> impl TypedValueParser<Value = output::Format> {
+    PossibleValuesParser::new(output::Format::ALL.map(|format| PossibleValue::new(format.name()).help(format.about())))
+        .map(|name| name.parse::<output::Format>().expect("a possible value"))
+}
+
+/// Accepts `--sort` names, listing each order in `--help`.
+fn sort_parser() -> impl TypedValueParser<Value = SortKey> {
+    PossibleValuesParser::new(SortKey::ALL.map(|key| PossibleValue::new(key.name()).help(key.about())))
+        .map(|name| name.parse::<SortKey>().expect("a possible value"))
+}
+
+/// Reports `error` on stderr and exits with the status for its class. A
+/// cancelled picker or prompt exits quietly.
+fn fail(error: Error) -> ! {
+    if !matches!(error, Error::Aborted) {
+        eprintln!("Error: {}", error);
+    }
+    exit(error.exit_code());
+}
+
+fn load_or_exit(paths: &Paths) -> Vec<Snippet> {
+    FileStore::new(&paths.store).snippets().unwrap_or_else(|e| fail(e))
+}
+
+/// Like `load_or_exit`, but an empty store is an error too.
+fn load_some_or_exit(paths: &Paths) -> Vec<Snippet> {
+    let snippets = load_or_exit(paths);
+    if snippets.is_empty() {
+        fail(Error::EmptyStore);
+    }
+    snippets
+}
+
+/// Converts a store written by an older release before anything reads it.
+/// Any file without a header is taken for one; in case it wasn't, such as
+/// a mistyped `--store`, the notice says how to undo it.
+fn auto_migrate(paths: &Paths) {
+    match migrate::needs_migration(&paths.store) {
+        Ok(false) => return,
+        Ok(true) => {}
+        Err(e) => fail(e),
+    }
+
+    match migrate::migrate(&paths.store) {
+        Ok(migrate::Outcome::Migrated(report)) => {
+            eprintln!(
+                "Upgraded {} to the record format ({} migrated, {} duplicates, {} blank). Backup: {}",
+                paths.store, report.migrated, report.duplicates, report.blank, report.backup
+            );
+            if paths.origin != Origin::Legacy {
+                eprintln!("If it wasn't a oneliners store, put it back with: mv {} {}", report.backup, paths.store);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => fail(e),
+    }
+}
+
+fn migrate_store(paths: &Paths) {
+    match migrate::migrate(&paths.store) {
+        Ok(migrate::Outcome::Migrated(report)) => {
+            println!("Migrated {} to the record format.", paths.store);
+            println!("  migrated:   {}", report.migrated);
+            println!("  duplicates: {}", report.duplicates);
+            println!("  blank:      {}", report.blank);
+            println!("  backup:     {}", report.backup);
+        }
+        Ok(migrate::Outcome::AlreadyCurrent) => println!("{} already uses the record format.", paths.store),
+        Ok(migrate::Outcome::NothingToMigrate) => println!("No oneliners stored yet."),
+        Err(e) => fail(e),
+    }
+}
+
+fn list_oneliners(listing: &Listing, format: Option<output::Format>, color: bool, paths: &Paths) {
+    let store = FileStore::new(&paths.store);
+    let snippets = store.snippets().unwrap_or_else(|e| fail(e));
+    if snippets.is_empty() && format.is_none() {
+        println!("No oneliners stored yet.");
+        return;
+    }
+    let shown = listing.apply(snippets);
+    if shown.is_empty() && format.is_none() && !listing.tags.is_empty() {
+        println!("No oneliners tagged {}.", listing.tags.join(", "));
+        return;
+    }
+    match format {
+        Some(format) => write_formatted(&shown.iter().collect::<Vec<_>>(), format),
+        None => terminal::page(&output::render_listing(&shown, io::stdout().is_terminal().then(|| terminal::size(&io::stdout()).0), color)),
+    }
+}
+
+fn write_formatted(snippets: &[&Snippet], format: output::Format) {
+    match output::write(&mut io::stdout().lock(), snippets, format) {
+        Ok(()) => {}
+        // Whoever reads our output may stop early (`| head`).
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => fail(Error::io("write", "stdout", e)),
+    }
+}
+
+/// Prints every match for `get --format`, best first, without picking one.
+fn print_matches(search: &str, paths: &Paths, options: &GetOptions, format: output::Format) {
+    let query = Query::parse(search).with_mode(options.mode);
+    let mut matches = FileStore::new(&paths.store).search(&query).unwrap_or_else(|e| fail(e));
+    matches.truncate(options.limit);
+    write_formatted(&matches.iter().map(|(snippet, _)| snippet).collect::<Vec<_>>(), format);
+    if matches.is_empty() {
+        fail(Error::NoMatches(search.to_string()));
+    }
+}
+
+fn list_tags(paths: &Paths) {
+    let counts = FileStore::new(&paths.store).tags().unwrap_or_else(|e| fail(e));
+    if counts.is_empty() {
+        println!("No tags in use.");
+        return;
+    }
+
+    let width = counts.iter().map(|(_, count)| count.to_string().len()).max().unwrap_or(1);
+    for (tag, count) in counts {
+        println!("{:>width$}  {}", count, tag, width = width);
+    }
+}
+
+/// How many history entries `store --pick` offers.
+const HISTORY_PICK_LIMIT: usize = 200;
+
+fn read_history(shell: Option<&str>, file: Option<&str>) -> Vec<String> {
+    let shell = shell.and_then(history::Shell::from_name).unwrap_or_else(history::Shell::detect);
+    let Some(path) = file.map(PathBuf::from).or_else(|| shell.history_file()) else {
+        fail(Error::Invalid(format!("unable to locate the {} history file", shell.name())));
+    };
+    history::read(shell, &path).context("read", &path.display().to_string()).unwrap_or_else(|e| fail(e))
+}
+
+fn pick_from_history(shell: Option<&str>, color: bool) -> Option<String> {
+    let recent = history::recent(&read_history(shell, None), HISTORY_PICK_LIMIT);
+    if recent.is_empty() {
+        fail(Error::Invalid("no commands found in your shell history".to_string()));
+    }
+
+    let items = recent.iter().map(|command| picker::Item { text: command.clone(), ..Default::default() }).collect();
+    pick(picker::Picker::new(items, "").prompt("history> ").color(color)).map(|selection| recent[selection.index].clone())
+}
+
+fn read_source(source: &StoreArgs, color: bool) -> Option<String> {
+    if source.last {
+        return match history::recent(&read_history(source.shell.as_deref(), None), 1).pop() {
+            Some(command) => Some(command),
+            None => fail(Error::Invalid("no commands found in your shell history".to_string())),
+        };
+    }
+    if source.pick {
+        return match pick_from_history(source.shell.as_deref(), color) {
+            Some(command) => Some(command),
+            None => fail(Error::Aborted),
+        };
+    }
+
+    let result = match (source.oneliner.as_deref(), source.file.as_deref()) {
+        (Some("-"), _) => io::read_to_string(io::stdin()),
+        (Some(oneliner), _) => Ok(oneliner.to_string()),
+        (None, Some(file)) => std::fs::read_to_string(file),
+        (None, None) => return None,
+    };
+    Some(result.context("read", source.file.as_deref().unwrap_or("stdin")).unwrap_or_else(|e| fail(e)))
+}
+
+fn store_oneliner(source: &StoreArgs, color: bool, paths: &Paths) {
+    if source.edit && source.oneliner.as_deref() == Some("-") {
+        fail(Error::Invalid("--edit can't be combined with reading from stdin".to_string()));
+    }
+
+    let tags = snippet::parse_tags(&source.tags).unwrap_or_else(|e| fail(e));
+    let text = read_source(source, color);
+    let mut draft = snippet::Draft {
+        command: snippet::normalize_command(text.as_deref().unwrap_or_default()),
+        description: source.description.as_deref().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string),
+        ..Default::default()
+    };
+    draft.tags = if tags.is_empty() && !source.no_auto_tag { snippet::suggest_tags(&draft.command) } else { tags };
+
+    if source.edit {
+        draft = match editor::edit_draft(&draft) {
+            Ok(Some(draft)) => draft,
+            Ok(None) => {
+                println!("Empty snippet, nothing stored.");
+                return;
+            }
+            Err(e) => fail(Error::io("edit", "snippet", e)),
+        };
+    } else if text.is_none() {
+        fail(Error::Invalid("nothing to store; pass a oneliner, - for stdin, --file, --last, --pick or --edit".to_string()));
+    }
+
+    store_draft(draft, paths);
+}
+
+fn store_draft(draft: snippet::Draft, paths: &Paths) {
+    let store = open_store(paths);
+    let snippet = match store.add(&draft) {
+        Ok(snippet) => snippet,
+        Err(Error::Duplicate(_)) => {
+            println!("Snippet already present.");
+            return;
+        }
+        Err(e) => fail(e),
+    };
+
+    println!("Snippet stored successfully! [{}]", paths.store);
+    if !snippet.tags.is_empty() {
+        println!("Tags: {}", snippet.tags.join(", "));
+    }
+    if snippet.dangerous {
+        let findings = store.review(&snippet, &snippet.command);
+        println!("Warning: this snippet looks destructive ({}).", findings.join(", "));
+        println!("Running it will ask for confirmation.");
+    }
+}
+
+fn import_history(shell: Option<&str>, file: Option<&str>, min_count: usize, all: bool, color: bool, paths: &Paths) {
+    let snippets = load_or_exit(paths);
+    let candidates: Vec<history::Candidate> = history::candidates(&read_history(shell, file))
+        .into_iter()
+        .filter(|candidate| {
+            candidate.count >= min_count && !snippets.iter().any(|snippet| snippet.command.trim() == candidate.command.trim())
+        })
+        .collect();
+    if candidates.is_empty() {
+        println!("Nothing new to import.");
+        return;
+    }
+
+    let chosen: Vec<usize> = if all {
+        (0..candidates.len()).collect()
+    } else {
+        if !io::stdin().is_terminal() {
+            fail(Error::Invalid("no terminal to pick commands on; pass --all to import every candidate".to_string()));
+        }
+        let items = candidates
+            .iter()
+            .map(|candidate| picker::Item {
+                text: candidate.command.clone(),
+                preview: vec![String::new(), match candidate.count {
+                    1 => "run once".to_string(),
+                    n => format!("run {} times", n),
+                }],
+                ..Default::default()
+            })
+            .collect();
+        match pick(picker::Picker::new(items, "").prompt("import> ").multi(true).color(color)) {
+            Some(selection) => selection.marked,
+            None => fail(Error::Aborted),
+        }
+    };
+
+    let commands: Vec<String> = chosen.iter().map(|&index| candidates[index].command.clone()).collect();
+    let imported = open_store(paths).import(&commands).unwrap_or_else(|e| fail(e));
+    let dangerous = imported.iter().filter(|snippet| snippet.dangerous).count();
+
+    let noun = if imported.len() == 1 { "snippet" } else { "snippets" };
+    println!("Imported {} {}. [{}]", imported.len(), noun, paths.store);
+    if dangerous > 0 {
+        println!("Warning: {} of them look destructive and will ask for confirmation when run.", dangerous);
+    }
+}
+
+/// Runs `picker` on the terminal; `None` when the user cancels.
+fn pick(picker: picker::Picker) -> Option<picker::Selection> {
+    picker::pick(picker).context("open", "/dev/tty").unwrap_or_else(|e| fail(e))
+}
+
+/// Counts a use of the snippet; `status` is the exit status when it was
+/// run rather than copied or printed.
+fn record_use(paths: &Paths, id: u64, status: Option<i32>) {
+    if let Err(e) = FileStore::new(&paths.store).record_use(id, status) {
+        eprintln!("Failed to record the use: {}", e);
+    }
+}
+
+fn load_rules(paths: &Paths) -> Vec<safety::Rule> {
+    safety::load_rules(&paths.rules).unwrap_or_else(|e| fail(e))
+}
+
+/// The store with the user's custom safety rules, for commands that add
+/// or change snippets.
+fn open_store(paths: &Paths) -> FileStore {
+    FileStore::new(&paths.store).with_rules(load_rules(paths))
+}
+
+/// Asks a yes/no question on stderr; anything but `y` or `yes` is no.
+fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        eprintln!("No terminal to confirm on. Pass --yes to go ahead anyway.");
+        return false;
+    }
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Asks the user to type `yes` before running a destructive command.
+fn confirm_dangerous(command: &str, findings: &[String]) -> bool {
+    eprintln!("This command looks destructive ({}):", findings.join(", "));
+    eprintln!();
+    eprintln!("    {}", command);
+    eprintln!();
+    if !io::stdin().is_terminal() {
+        eprintln!("Refusing to run it without a terminal to confirm on.");
+        return false;
+    }
+
+    eprint!("Type 'yes' to run it: ");
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    answer.trim() == "yes"
+}
+
+/// Whether `command` may run, asking first as the `confirm` setting says.
+/// `findings` are what the safety check found wrong with it.
+fn approve(command: &str, findings: &[String], confirm_with: Confirm) -> bool {
+    match confirm_with {
+        Confirm::Never => true,
+        _ if !findings.is_empty() => confirm_dangerous(command, findings),
+        Confirm::Always => {
+            eprintln!("    {}", command);
+            confirm("Run it?")
+        }
+        Confirm::Destructive => true,
+    }
+}
+
+/// Runs an already expanded snippet and returns its exit code. Destructive
+/// commands need confirmation first, unless the `confirm` setting says
+/// otherwise.
+fn execute_snippet(paths: &Paths, snippet: &Snippet, command: &str, options: &GetOptions) -> i32 {
+    let findings = open_store(paths).review(snippet, command);
+    if !approve(command, &findings, options.confirm) {
+        eprintln!("Not running it.");
+        return EXIT_ABORTED;
+    }
+
+    let shell = run::shell(options.shell.as_deref());
+    let status = match run::execute(command, &shell) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Failed to start {}: {}", shell, e);
+            127
+        }
+    };
+    record_use(paths, snippet.id, Some(status));
+    status
+}
+
+fn edit_snippet(snippet: &Snippet, paths: &Paths) {
+    let original = snippet::Draft::from_snippet(snippet);
+    let edited = match editor::edit_draft(&original) {
+        Ok(Some(draft)) => draft,
+        Ok(None) => {
+            println!("Snippet left unchanged.");
+            return;
+        }
+        Err(e) => fail(Error::io("edit", "snippet", e)),
+    };
+
+    if edited == original {
+        println!("Snippet left unchanged.");
+        return;
+    }
+    // The snippet may have been deleted while the editor was open.
+    open_store(paths).replace(snippet.id, &edited).unwrap_or_else(|e| fail(e));
+    println!("Snippet updated.");
+}
+
+/// Finds the snippet `target` refers to; see `SnippetStore::resolve`.
+/// When it matches several snippets, the user picks one on a terminal.
+fn resolve_snippet(target: &str, color: bool, paths: &Paths) -> Snippet {
+    match FileStore::new(&paths.store).resolve(target, search::Mode::Fuzzy) {
+        Ok(snippet) => snippet,
+        Err(Error::Ambiguous { .. }) if io::stdin().is_terminal() => {
+            let snippets = load_or_exit(paths);
+            let items = snippets.iter().map(picker::Item::from_snippet).collect();
+            match pick(picker::Picker::new(items, target).color(color)) {
+                Some(selection) => snippets[selection.index].clone(),
+                None => fail(Error::Aborted),
+            }
+        }
+        Err(e) => fail(e),
+    }
+}
+
+fn edit_oneliner(target: &str, color: bool, paths: &Paths) {
+    edit_snippet(&resolve_snippet(target, color, paths), paths);
+}
+
+fn delete_oneliners(target: Option<&str>, pattern: Option<&str>, ask: bool, color: bool, paths: &Paths) {
+    let snippets = load_some_or_exit(paths);
+
+    let doomed: Vec<u64> = match (target, pattern) {
+        (_, Some("")) => fail(Error::Invalid("--match needs a non-empty pattern".to_string())),
+        (_, Some(pattern)) => snippets.iter().filter(|s| search::contains(pattern, &s.command)).map(|s| s.id).collect(),
+        (Some(target), None) => vec![resolve_snippet(target, color, paths).id],
+        (None, None) => Vec::new(),
+    };
+    if doomed.is_empty() {
+        fail(Error::NoMatches(pattern.unwrap_or_default().to_string()));
+    }
+
+    let noun = if doomed.len() == 1 { "snippet" } else { "snippets" };
+    if ask {
+        eprintln!("This will delete {} {}:", doomed.len(), noun);
+        for snippet in snippets.iter().filter(|s| doomed.contains(&s.id)) {
+            let prefix = format!("  {}: ", snippet.id);
+            eprintln!("{}{}", prefix, snippet::indent_continuation(&snippet.command, prefix.len()));
+        }
+        if !confirm("Delete?") {
+            println!("Nothing deleted.");
+            fail(Error::Aborted);
+        }
+    }
+
+    let deleted = FileStore::new(&paths.store).remove(&doomed).unwrap_or_else(|e| fail(e));
+    let noun = if deleted == 1 { "snippet" } else { "snippets" };
+    println!("Deleted {} {}.", deleted, noun);
+}
+
+fn undo_operations(count: usize, paths: &Paths) {
+    match FileStore::new(&paths.store).undo(count) {
+        Ok(undone) if undone.is_empty() => println!("Nothing to undo."),
+        Ok(undone) => {
+            for operation in &undone {
+                println!("Undid {}", operation.describe());
+            }
+        }
+        Err(e) => fail(e),
+    }
+}
+
+fn show_history(id: u64, restore: Option<usize>, paths: &Paths) {
+    let store = FileStore::new(&paths.store);
+    let versions = store.versions(id).unwrap_or_else(|e| fail(e));
+    if versions.is_empty() {
+        fail(Error::NoHistory(id));
+    }
+
+    let Some(number) = restore else {
+        for (i, version) in versions.iter().enumerate() {
+            let prefix = format!("{:>3}  {}  {:<8} ", i + 1, snippet::format_time(version.at), version.op);
+            match &version.snippet {
+                Some(snippet) => {
+                    println!("{}{}", prefix, snippet::indent_continuation(&snippet.command, prefix.chars().count()))
+                }
+                None => println!("{}(deleted)", prefix),
+            }
+        }
+        return;
+    };
+
+    let Some(version) = number.checked_sub(1).and_then(|i| versions.get(i)) else {
+        fail(Error::Invalid(format!("snippet {} has versions 1 to {}", id, versions.len())));
+    };
+    let Some(snippet) = &version.snippet else {
+        fail(Error::Invalid(format!("version {} is the deletion of snippet {}; pick another one", number, id)));
+    };
+    match store.restore(snippet) {
+        Ok(()) => println!("Restored version {} of snippet {}.", number, id),
+        Err(e) => fail(e),
+    }
+}
+
+/// Settings shared by the different ways `get` can pick a snippet.
+struct GetOptions<'a> {
+    mode: search::Mode,
+    limit: usize,
+    target: Target<'a>,
+    vars: HashMap<String, String>,
+    refresh: bool,
+    shell: Option<String>,
+    color: bool,
+    /// Whether the picker highlights matches; see `Color::enabled_on_tty`.
+    picker_color: bool,
+    confirm: Confirm,
+}
+
+fn get_options<'a>(query: &QueryArgs, config: &'a Config, target: Target<'a>) -> GetOptions<'a> {
+    let vars = placeholder::parse_vars(&query.vars).unwrap_or_else(|e| fail(e));
+    GetOptions {
+        mode: config.search(),
+        limit: config.limit(),
+        target,
+        vars,
+        refresh: query.refresh,
+        shell: config.shell().map(str::to_string),
+        color: config.color().enabled(),
+        picker_color: config.color().enabled_on_tty(),
+        confirm: config.confirm(),
+    }
+}
+
+/// Asks for placeholder values on the terminal: generator output in a
+/// picker, everything else on stderr and stdin.
+struct TerminalPrompt {
+    /// Generators run through the shell just like snippets, so they get
+    /// the same safety check and confirmation.
+    rules: Vec<safety::Rule>,
+    confirm: Confirm,
+    color: bool,
+}
+
+impl placeholder::Prompt for TerminalPrompt {
+    fn approve(&mut self, placeholder: &placeholder::Placeholder, command: &str) -> bool {
+        if !approve(command, &safety::check(command, &self.rules), self.confirm) {
+            eprintln!("Not running {}.", command);
+            return false;
+        }
+        eprintln!("Running {} for <{}>...", command, placeholder.name);
+        true
+    }
+
+    fn no_choices(&mut self, command: &str, error: Option<&Error>) {
+        match error {
+            Some(e) => eprintln!("{}", e),
+            None => eprintln!("{} printed nothing.", command),
+        }
+    }
+
+    fn choose(&mut self, placeholder: &placeholder::Placeholder, choices: &[String]) -> oneliners::Result<Option<usize>> {
+        let items = choices.iter().map(|choice| picker::Item { text: choice.clone(), ..Default::default() }).collect();
+        let picker = picker::Picker::new(items, "").prompt(&format!("{}> ", placeholder.name)).color(self.color);
+        Ok(picker::pick(picker).context("open", "/dev/tty")?.map(|selection| selection.index))
+    }
+
+    fn ask(&mut self, placeholder: &placeholder::Placeholder, suggestion: Option<&str>, recent: &[String]) -> oneliners::Result<Option<String>> {
+        loop {
+            let mut question = placeholder.name.clone();
+            let others: Vec<&str> = recent.iter().map(String::as_str).filter(|v| Some(*v) != suggestion).take(4).collect();
+            if !others.is_empty() {
+                question.push_str(&format!(" (recent: {})", others.join(", ")));
+            }
+            if let Some(suggestion) = suggestion {
+                question.push_str(&format!(" [{}]", suggestion));
+            }
+            eprint!("{}: ", question);
+
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).context("read", "stdin")? == 0 {
+                return Ok(None);
+            }
+            let value = line.trim_end_matches(['\n', '\r']);
+            if !value.is_empty() {
+                return Ok(Some(value.to_string()));
+            }
+            if let Some(suggestion) = suggestion {
+                return Ok(Some(suggestion.to_string()));
+            }
+        }
+    }
+}
+
+/// Fills in the placeholders of a selected snippet, prompting for missing
+/// values when `interactive`.
+fn expand_placeholders(command: &str, paths: &Paths, options: &GetOptions, interactive: bool) -> String {
+    if placeholder::placeholders(command).is_empty() {
+        return placeholder::expand(command, &HashMap::new());
+    }
+
+    let mut recent = placeholder::RecentValues::load(&paths.values);
+    let mut cache = generator::Cache::load(&paths.cache, options.refresh);
+    let mut prompt = TerminalPrompt { rules: load_rules(paths), confirm: options.confirm, color: options.picker_color };
+    let prompt = interactive.then_some(&mut prompt as &mut dyn placeholder::Prompt);
+    match placeholder::fill(command, &options.vars, &mut recent, &mut cache, prompt) {
+        Ok(expanded) => {
+            if let Err(e) = recent.save() {
+                eprintln!("Failed to remember placeholder values: {}", e);
+            }
+            if let Err(e) = cache.save() {
+                eprintln!("Failed to cache generator output: {}", e);
+            }
+            expanded
+        }
+        Err(e) => fail(e),
+    }
+}
+
+fn pick_oneliner(search: &str, paths: &Paths, options: &GetOptions) {
+    let snippets = load_some_or_exit(paths);
+    let items = snippets.iter().map(picker::Item::from_snippet).collect();
+    let picker = picker::Picker::new(items, search).mode(options.mode).actions(true).color(options.picker_color);
+    let Some(selection) = pick(picker) else {
+        fail(Error::Aborted);
+    };
+
+    let snippet = &snippets[selection.index];
+    match selection.action {
+        picker::Action::Accept => {
+            deliver(&options.target, &expand_placeholders(&snippet.command, paths, options, true));
+            record_use(paths, snippet.id, None);
+        }
+        picker::Action::Edit => edit_snippet(snippet, paths),
+        picker::Action::Run => {
+            let command = expand_placeholders(&snippet.command, paths, options, true);
+            exit(execute_snippet(paths, snippet, &command, options))
+        }
+    }
+}
+
+fn select_oneliner(search: &str, paths: &Paths, options: &GetOptions) {
+    let query = Query::parse(search).with_mode(options.mode);
+    let oneliners = FileStore::new(&paths.store).find(&query, options.limit).unwrap_or_else(|e| fail(e));
+
+    for (i, (oneliner, m)) in oneliners.iter().enumerate() {
+        let prefix = format!("{}: ", i + 1);
+        let command = if options.color {
+            search::highlight(&oneliner.command, &m.positions)
+        } else {
+            oneliner.command.clone()
+        };
+        println!("{}{}", prefix, snippet::indent_continuation(&command, prefix.len()));
+    }
+
+    println!("Select a oneliner (1-{}):", oneliners.len());
+    let mut selection = String::new();
+    if io::stdin().read_line(&mut selection).is_err() {
+        return;
+    }
+    if let Ok(choice) = selection.trim().parse::<usize>()
+        && choice > 0 && choice <= oneliners.len() {
+        let command = expand_placeholders(&oneliners[choice - 1].0.command, paths, options, false);
+        deliver(&options.target, &command);
+        record_use(paths, oneliners[choice - 1].0.id, None);
+    }
+}
+
+/// Prints the best match without prompting, for `get --print` in a pipeline.
+fn print_best_match(search: &str, paths: &Paths, options: &GetOptions) {
+    let query = Query::parse(search).with_mode(options.mode);
+    let snippet = match FileStore::new(&paths.store).find(&query, 1) {
+        Ok(mut matches) => matches.remove(0).0,
+        Err(Error::EmptyStore) => fail(Error::NoMatches(search.to_string())),
+        Err(e) => fail(e),
+    };
+    println!("{}", expand_placeholders(&snippet.command, paths, options, false));
+    record_use(paths, snippet.id, None);
+}
+
+/// Picks a snippet and runs it. Without a terminal nobody sees what was
+/// picked, so the search has to name exactly one snippet: by id, or as an
+/// exact search only it matches.
+fn run_oneliner(search: &str, paths: &Paths, options: &GetOptions, dry_run: bool) {
+    let interactive = io::stdin().is_terminal();
+    let snippet = if interactive {
+        let snippets = load_some_or_exit(paths);
+        let items = snippets.iter().map(picker::Item::from_snippet).collect();
+        match pick(picker::Picker::new(items, search).mode(options.mode).color(options.picker_color)) {
+            Some(selection) => snippets[selection.index].clone(),
+            None => fail(Error::Aborted),
+        }
+    } else if search.trim().is_empty() {
+        fail(Error::Invalid("no terminal to pick a snippet on; pass its id or a search only it matches".to_string()));
+    } else {
+        FileStore::new(&paths.store).resolve(search, search::Mode::Exact).unwrap_or_else(|e| fail(e))
+    };
+
+    let command = expand_placeholders(&snippet.command, paths, options, interactive);
+    if dry_run {
+        println!("{}", command);
+        return;
+    }
+    exit(execute_snippet(paths, &snippet, &command, options));
+}
+
+/// Where a selected snippet goes.
+enum Target<'a> {
+    Stdout,
+    /// The clipboard, with the backend preference from `--clipboard`. The
+    /// backend is only looked up once something is actually copied.
+    Clipboard(Option<&'a str>),
+}
+
+fn deliver(target: &Target, text: &str) {
+    match target {
+        Target::Stdout => println!("{}", text),
+        Target::Clipboard(preference) => copy_to_clipboard(*preference, text),
+    }
+}
+
+fn copy_to_clipboard(preference: Option<&str>, text: &str) {
+    let clipboard = clipboard::resolve(preference).unwrap_or_else(|e| fail(e));
+
+    match clipboard.copy(text) {
+        Ok(()) if clipboard.name() == "stdout" => {}
+        Ok(()) => println!("Snippet copied to clipboard! [{}]", clipboard.name()),
+        Err(e) => fail(Error::io("copy to", clipboard.name(), e)),
+    }
+}
+
+// fn get_zsh_completions_file() -> String {
+//     match home_dir() {
+//         Some(path) => {
+//             return path.to_str().expect("Invalid home directory").to_owned() + "/.oh-my-zsh/completions";
+//         },
+//         None => panic!("Unable to locate .oh-my-zsh/completions file.")
+//     }
+// }
+
+// fn store_in_zsh_completions(oneliner: &str) {
+//     println!("Storing in zsh completions...");
+//     let zsh_completions_path = get_zsh_completions_file();
+//     store_oneliner(oneliner, &zsh_completions_path);
+// }
+
+fn show_paths(paths: &Paths) {
+    let rows = [
+        ("store", format!("{} ({})", paths.store, paths.origin.describe())),
+        ("log", paths.log()),
+        ("config", paths.config.clone()),
+        ("rules", paths.rules.clone()),
+        ("values", paths.values.clone()),
+        ("cache", paths.cache.clone()),
+    ];
+    for (name, path) in rows {
+        println!("{:<7} {}", name, path);
+    }
+}
+
+/// Adds the command-line flags that override settings as the last layer
+/// of `config`.
+fn apply_flags(config: &mut Config, cli: &Cli) -> oneliners::Result<()> {
+    config.set_flag("store", "--store", cli.store.clone().filter(|path| !path.is_empty()))?;
+    config.set_flag("clipboard", "--clipboard", cli.clipboard.clone())?;
+    config.set_flag("color", "--color", cli.color.clone())?;
+    match &cli.command {
+        Commands::Get { query, limit, .. } => {
+            config.set_flag("limit", "--limit", limit.map(|limit| limit.to_string()))?;
+            config.set_flag("search", "--exact", query.exact.then(|| "exact".to_string()))?;
+        }
+        Commands::Run { query, shell, .. } => {
+            config.set_flag("search", "--exact", query.exact.then(|| "exact".to_string()))?;
+            config.set_flag("shell", "--shell", shell.clone())?;
+        }
+        Commands::Delete { yes, .. } => config.set_flag("confirm-delete", "--yes", yes.then(|| "never".to_string()))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn config_scope(scope: &ScopeArgs) -> Scope {
+    if scope.system {
+        Scope::System
+    } else if scope.project {
+        Scope::Project
+    } else {
+        Scope::User
+    }
+}
+
+fn config_command(action: ConfigAction, config: &Config) {
+    match action {
+        ConfigAction::Get { key, show_origin } => {
+            if config::key(&key).is_none() {
+                fail(Error::Invalid(format!("unknown setting '{}'", key)));
+            }
+            // Like `git config`, an unset key prints nothing.
+            let Some(setting) = config.setting(&key) else {
+                exit(EXIT_NOT_FOUND);
+            };
+            if show_origin {
+                println!("{}\t{}", setting.source, setting.value);
+            } else {
+                println!("{}", setting.value);
+            }
+        }
+        ConfigAction::Set { key, value, scope } => {
+            let scope = config_scope(&scope);
+            let path = config::scope_file(scope).unwrap_or_else(|e| fail(e));
+            config::write_setting(&path, scope, &key, Some(&value)).unwrap_or_else(|e| fail(e));
+        }
+        ConfigAction::Unset { key, scope } => {
+            let scope = config_scope(&scope);
+            let path = config::scope_file(scope).unwrap_or_else(|e| fail(e));
+            config::write_setting(&path, scope, &key, None).unwrap_or_else(|e| fail(e));
+        }
+        ConfigAction::List { show_origin } => {
+            for (key, setting) in config.list() {
+                if show_origin {
+                    println!("{}\t{}={}", setting.source, key.name, setting.value);
+                } else {
+                    println!("{}={}", key.name, setting.value);
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    // Die quietly when whoever reads our output goes away (`| head`), like
+    // other command-line tools, instead of panicking in `println!`.
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    let cli = Cli::parse();
+
+    let mut config = Config::load().unwrap_or_else(|e| fail(e));
+    for warning in config.warnings() {
+        eprintln!("Warning: {}; ignoring it.", warning);
+    }
+    apply_flags(&mut config, &cli).unwrap_or_else(|e| fail(e));
+    let paths = Paths::resolve(&config).unwrap_or_else(|e| fail(e));
+
+    if !matches!(cli.command, Commands::Migrate | Commands::Paths | Commands::Config { .. }) {
+        auto_migrate(&paths);
+    }
+
+    match cli.command {
+        Commands::Store { source } => store_oneliner(&source, config.color().enabled_on_tty(), &paths),
+        Commands::Get { query, print, format, .. } => {
+            let target = if print { Target::Stdout } else { Target::Clipboard(config.clipboard()) };
+            let options = get_options(&query, &config, target);
+            let search = query.search.unwrap_or_default();
+            if let Some(format) = format {
+                print_matches(&search, &paths, &options, format);
+            } else if io::stdin().is_terminal() {
+                pick_oneliner(&search, &paths, &options);
+            } else if print {
+                print_best_match(&search, &paths, &options);
+            } else {
+                select_oneliner(&search, &paths, &options);
+            }
+        },
+        Commands::Run { query, dry_run, .. } => {
+            let options = get_options(&query, &config, Target::Stdout);
+            run_oneliner(&query.search.unwrap_or_default(), &paths, &options, dry_run);
+        },
+        Commands::List { tags, limit, offset, sort, reverse, format } => {
+            let listing = Listing { tags: snippet::parse_tags(&tags).unwrap_or_else(|e| fail(e)), sort, reverse, offset, limit };
+            list_oneliners(&listing, format, config.color().enabled(), &paths);
+        },
+        Commands::Tags => list_tags(&paths),
+        Commands::Edit { target } => edit_oneliner(&target, config.color().enabled_on_tty(), &paths),
+        Commands::Delete { target, pattern, .. } => {
+            delete_oneliners(target.as_deref(), pattern.as_deref(), config.confirm_delete(), config.color().enabled_on_tty(), &paths);
+        },
+        Commands::Undo { count } => undo_operations(count, &paths),
+        Commands::History { id, restore } => show_history(id, restore, &paths),
+        Commands::Import { source } => match source {
+            ImportSource::History { shell, file, min_count, all } => {
+                import_history(shell.as_deref(), file.as_deref(), min_count, all, config.color().enabled_on_tty(), &paths);
+            }
+        },
+        Commands::Migrate => migrate_store(&paths),
+        Commands::Paths => show_paths(&paths),
+        Commands::Config { action } => config_command(action, &config),
+    }
+}
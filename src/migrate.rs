@@ -0,0 +1,127 @@
+use crate::error::{Context, Result};
+use crate::snippet::Snippet;
+use crate::store::{self, Format};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub migrated: usize,
+    pub duplicates: usize,
+    pub blank: usize,
+    pub backup: String,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    /// The store is missing or empty; there is nothing to convert.
+    NothingToMigrate,
+    /// The store already uses the record format.
+    AlreadyCurrent,
+    Migrated(MigrationReport),
+}
+
+pub fn needs_migration(file_path: &str) -> Result<bool> {
+    Ok(store::detect_format(file_path)? == Format::Legacy)
+}
+
+fn backup_path(file_path: &str) -> String {
+    let base = format!("{}.bak-{}", file_path, crate::snippet::now());
+    let mut candidate = base.clone();
+    let mut n = 1;
+    while Path::new(&candidate).exists() {
+        candidate = format!("{}.{}", base, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// Converts a legacy plain-line store to the record format, leaving a
+/// timestamped copy of the original next to it. Running it again on an
+/// already converted store is a no-op.
+pub fn migrate(file_path: &str) -> Result<Outcome> {
+    let _lock = store::lock(file_path)?;
+    let lines = store::read_lines(file_path)?;
+    match store::format_of(&lines) {
+        Format::Empty => return Ok(Outcome::NothingToMigrate),
+        Format::Records(_) => return Ok(Outcome::AlreadyCurrent),
+        Format::Legacy => {}
+    }
+
+    let stamp = store::modified_secs(file_path);
+    let mut report = MigrationReport::default();
+    let mut snippets: Vec<Snippet> = Vec::new();
+    for line in &lines {
+        let command = line.trim();
+        if command.is_empty() {
+            report.blank += 1;
+        } else if snippets.iter().any(|s| s.command == command) {
+            report.duplicates += 1;
+        } else {
+            let mut snippet = Snippet::new(snippets.len() as u64 + 1, command);
+            snippet.created = stamp;
+            snippet.updated = stamp;
+            snippets.push(snippet);
+        }
+    }
+    report.migrated = snippets.len();
+
+    report.backup = backup_path(file_path);
+    fs::copy(file_path, &report.backup).context("back up", file_path)?;
+    store::save(file_path, &snippets)?;
+
+    Ok(Outcome::Migrated(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_store(name: &str, contents: &str) -> (std::path::PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("oneliners-migrate-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("oneliners").to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn counts_what_it_migrated_dropped_and_skipped() {
+        let legacy = "ls -la\n\ndu -sh *\n  ls -la  \n\t\ngrep -rn TODO .\n";
+        let (dir, path) = scratch_store("counts", legacy);
+        assert!(needs_migration(&path).unwrap());
+
+        let Outcome::Migrated(report) = migrate(&path).unwrap() else { panic!("expected a migration") };
+        assert_eq!((report.migrated, report.duplicates, report.blank), (3, 1, 2));
+        assert_eq!(fs::read_to_string(&report.backup).unwrap(), legacy);
+
+        let snippets = store::load(&path).unwrap();
+        let commands: Vec<(u64, &str)> = snippets.iter().map(|s| (s.id, s.command.as_str())).collect();
+        assert_eq!(commands, [(1, "ls -la"), (2, "du -sh *"), (3, "grep -rn TODO .")]);
+        assert!(!needs_migration(&path).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let (dir, path) = scratch_store("twice", "ls -la\n");
+        assert!(matches!(migrate(&path).unwrap(), Outcome::Migrated(_)));
+        let converted = fs::read_to_string(&path).unwrap();
+
+        assert!(matches!(migrate(&path).unwrap(), Outcome::AlreadyCurrent));
+        assert_eq!(fs::read_to_string(&path).unwrap(), converted);
+        let backups = fs::read_dir(&dir).unwrap().filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().contains(".bak-")).count();
+        assert_eq!(backups, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn empty_stores_need_nothing() {
+        let (dir, path) = scratch_store("empty", "\n\n");
+        assert!(!needs_migration(&path).unwrap());
+        assert!(matches!(migrate(&path).unwrap(), Outcome::NothingToMigrate));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\n\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
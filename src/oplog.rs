@@ -0,0 +1,203 @@
+use crate::error::{Context, Error, Result};
+use crate::snippet::{self, Snippet};
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+/// What one operation did to one snippet. `before` is `None` for a snippet
+/// the operation created, `after` is `None` for one it deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub id: u64,
+    pub before: Option<Snippet>,
+    pub after: Option<Snippet>,
+}
+
+/// One entry of the operation log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub seq: u64,
+    pub at: u64,
+    /// `store`, `edit`, `delete`, `import`, `restore` or `undo`.
+    pub op: String,
+    /// For `undo`, the operations it reverted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undoes: Vec<u64>,
+    pub changes: Vec<Change>,
+}
+
+impl Operation {
+    /// One line saying what the operation did, for `undo`.
+    pub fn describe(&self) -> String {
+        let ids: Vec<String> = self.changes.iter().map(|change| change.id.to_string()).collect();
+        let noun = if ids.len() == 1 { "snippet" } else { "snippets" };
+        format!("{} of {} {} ({})", self.op, noun, ids.join(", "), snippet::format_time(self.at))
+    }
+}
+
+/// The operation log lives next to the store, one JSON operation per line.
+pub fn log_path(file_path: &str) -> String {
+    format!("{}.log", file_path)
+}
+
+/// Every operation in the log, oldest first. A line that doesn't parse is
+/// an error, since undo and history would be wrong without it.
+pub fn read(file_path: &str) -> Result<Vec<Operation>> {
+    let path = log_path(file_path);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("read", &path),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| Error::parse(&path, Some(i + 1), e.to_string())))
+        .collect()
+}
+
+/// The highest snippet id the log mentions, or 0. Ids of deleted snippets
+/// stay in the log, so new snippets never take over their history.
+pub fn max_id(file_path: &str) -> Result<u64> {
+    Ok(read(file_path)?.iter().flat_map(|op| &op.changes).map(|change| change.id).max().unwrap_or(0))
+}
+
+/// The changes that turn `before` into `after`.
+pub fn diff(before: &[Snippet], after: &[Snippet]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for old in before {
+        match after.iter().find(|s| s.id == old.id) {
+            Some(new) if new == old => {}
+            new => changes.push(Change { id: old.id, before: Some(old.clone()), after: new.cloned() }),
+        }
+    }
+    for new in after {
+        if !before.iter().any(|s| s.id == new.id) {
+            changes.push(Change { id: new.id, before: None, after: Some(new.clone()) });
+        }
+    }
+    changes
+}
+
+/// The sequence number for the next operation in `operations`.
+fn next_seq(operations: &[Operation]) -> u64 {
+    operations.last().map(|last| last.seq).unwrap_or(0) + 1
+}
+
+/// Reads the log to number the next operation. Call it before changing the
+/// store, so a log that doesn't parse leaves the store untouched instead of
+/// losing the record of the change. The caller must hold the store lock.
+pub fn prepare(file_path: &str) -> Result<u64> {
+    Ok(next_seq(&read(file_path)?))
+}
+
+/// Appends operation `seq`, as numbered by `prepare`, to the log. The
+/// caller must hold the store lock.
+pub fn record(file_path: &str, seq: u64, op: &str, undoes: Vec<u64>, changes: Vec<Change>) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let operation = Operation { seq, at: snippet::now(), op: op.to_string(), undoes, changes };
+    let path = log_path(file_path);
+    let line = serde_json::to_string(&operation).context("write", &path)?;
+    let mut log = OpenOptions::new().create(true).append(true).open(&path).context("write", &path)?;
+    writeln!(log, "{}", line).context("write", &path)?;
+    log.sync_all().context("write", &path)
+}
+
+/// Puts `version` of snippet `id` into the store, or removes the snippet
+/// when `version` is `None`. Usage statistics are kept from the current
+/// copy, since they are not part of the history.
+fn apply(snippets: &mut Vec<Snippet>, id: u64, version: Option<&Snippet>) {
+    let current = snippets.iter().position(|s| s.id == id);
+    match (current, version) {
+        (Some(i), Some(version)) => {
+            let current = &snippets[i];
+            snippets[i] = Snippet {
+                last_run: current.last_run,
+                last_status: current.last_status,
+                last_used: current.last_used,
+                use_count: current.use_count,
+                ..version.clone()
+            };
+        }
+        (Some(i), None) => {
+            snippets.remove(i);
+        }
+        (None, Some(version)) => {
+            let at = snippets.iter().position(|s| s.id > id).unwrap_or(snippets.len());
+            snippets.insert(at, version.clone());
+        }
+        (None, None) => {}
+    }
+}
+
+/// The latest `count` operations that haven't been undone yet, newest first.
+/// Undo entries themselves are never undone.
+fn undoable(operations: &[Operation], count: usize) -> Vec<&Operation> {
+    let undone: HashSet<u64> = operations.iter().flat_map(|op| op.undoes.iter().copied()).collect();
+    operations
+        .iter()
+        .rev()
+        .filter(|op| op.op != "undo" && !undone.contains(&op.seq))
+        .take(count)
+        .collect()
+}
+
+/// Reverts the last `count` operations, newest first, and logs that as one
+/// `undo` operation. Returns the operations that were reverted.
+pub fn undo(file_path: &str, count: usize) -> Result<Vec<Operation>> {
+    let _lock = store::lock(file_path)?;
+    let operations = read(file_path)?;
+    let targets: Vec<Operation> = undoable(&operations, count).into_iter().cloned().collect();
+    if targets.is_empty() {
+        return Ok(targets);
+    }
+
+    let before = store::load(file_path)?;
+    let mut snippets = before.clone();
+    for operation in &targets {
+        for change in operation.changes.iter().rev() {
+            apply(&mut snippets, change.id, change.before.as_ref());
+        }
+    }
+    store::save(file_path, &snippets)?;
+    let undoes = targets.iter().map(|op| op.seq).collect();
+    record(file_path, next_seq(&operations), "undo", undoes, diff(&before, &snippets))?;
+    Ok(targets)
+}
+
+/// A past state of a snippet: what it looked like after `op` ran, or
+/// `None` if the operation deleted it.
+pub struct Version {
+    pub at: u64,
+    pub op: String,
+    pub snippet: Option<Snippet>,
+}
+
+/// Every logged state of snippet `id`, oldest first.
+pub fn versions(file_path: &str, id: u64) -> Result<Vec<Version>> {
+    let mut versions = Vec::new();
+    for operation in read(file_path)? {
+        for change in operation.changes.into_iter().filter(|change| change.id == id) {
+            versions.push(Version { at: operation.at, op: operation.op.clone(), snippet: change.after });
+        }
+    }
+    Ok(versions)
+}
+
+/// Makes `version` the current state of its snippet, bringing it back if it
+/// was deleted, and logs that as a `restore` operation.
+pub fn restore(file_path: &str, version: &Snippet) -> Result<()> {
+    let _lock = store::lock(file_path)?;
+    let seq = prepare(file_path)?;
+    let before = store::load(file_path)?;
+    let mut snippets = before.clone();
+    let restored = Snippet { updated: snippet::now(), ..version.clone() };
+    apply(&mut snippets, version.id, Some(&restored));
+    store::save(file_path, &snippets)?;
+    record(file_path, seq, "restore", Vec::new(), diff(&before, &snippets))
+}
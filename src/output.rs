@@ -0,0 +1,344 @@
+use crate::error::{Error, Result};
+use crate::picker;
+use crate::snippet::Snippet;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Machine-readable output formats for `list` and `get`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Jsonl,
+    Csv,
+    Tsv,
+    Plain,
+    Nul,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [Format::Json, Format::Jsonl, Format::Csv, Format::Tsv, Format::Plain, Format::Nul];
+
+    /// What `--format` calls it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Plain => "plain",
+            Format::Nul => "nul",
+        }
+    }
+
+    pub fn about(self) -> &'static str {
+        match self {
+            Format::Json => "One JSON array of records",
+            Format::Jsonl => "One JSON record per line",
+            Format::Csv => "Comma-separated values with a header row",
+            Format::Tsv => "Tab-separated values with a header row",
+            Format::Plain => "Just the commands, one per line",
+            Format::Nul => "Just the commands, each followed by a NUL byte",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Format> {
+        Format::ALL.into_iter().find(|format| format.name() == name).ok_or_else(|| Error::Invalid(format!("unknown format '{}'", name)))
+    }
+}
+
+/// The fields every format exposes, in this order. Names and meanings are
+/// stable; new fields are only ever added at the end.
+pub const FIELDS: &[&str] = &[
+    "id",
+    "command",
+    "description",
+    "tags",
+    "created",
+    "updated",
+    "last_used",
+    "use_count",
+    "last_run",
+    "last_status",
+    "dangerous",
+];
+
+/// One snippet as it appears in JSON output. Timestamps are seconds since
+/// the Unix epoch; fields without a value are `null` rather than left out.
+#[derive(Serialize)]
+struct Record<'a> {
+    id: u64,
+    command: &'a str,
+    description: Option<&'a str>,
+    tags: &'a [String],
+    created: u64,
+    updated: u64,
+    last_used: Option<u64>,
+    use_count: u64,
+    last_run: Option<u64>,
+    last_status: Option<i32>,
+    dangerous: bool,
+}
+
+impl<'a> Record<'a> {
+    fn new(snippet: &'a Snippet) -> Record<'a> {
+        Record {
+            id: snippet.id,
+            command: &snippet.command,
+            description: snippet.description.as_deref(),
+            tags: &snippet.tags,
+            created: snippet.created,
+            updated: snippet.updated,
+            last_used: snippet.last_used,
+            use_count: snippet.use_count,
+            last_run: snippet.last_run,
+            last_status: snippet.last_status,
+            dangerous: snippet.dangerous,
+        }
+    }
+
+    /// The fields as text, in `FIELDS` order, for CSV and TSV. Tags are
+    /// separated by spaces and missing values are empty.
+    fn columns(&self) -> Vec<String> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        vec![
+            self.id.to_string(),
+            self.command.to_string(),
+            optional(self.description.map(str::to_string)),
+            self.tags.join(" "),
+            self.created.to_string(),
+            self.updated.to_string(),
+            optional(self.last_used.map(|t| t.to_string())),
+            self.use_count.to_string(),
+            optional(self.last_run.map(|t| t.to_string())),
+            optional(self.last_status.map(|s| s.to_string())),
+            self.dangerous.to_string(),
+        ]
+    }
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes backslashes, tabs and line breaks so every record stays on one
+/// line.
+fn tsv_field(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+pub fn write<W: Write>(out: &mut W, snippets: &[&Snippet], format: Format) -> io::Result<()> {
+    let records: Vec<Record> = snippets.iter().map(|snippet| Record::new(snippet)).collect();
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => {
+            for record in &records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, escape): (&str, fn(&str) -> String) = match format {
+                Format::Csv => (",", csv_field),
+                _ => ("\t", tsv_field),
+            };
+            writeln!(out, "{}", FIELDS.join(separator))?;
+            for record in &records {
+                let columns: Vec<String> = record.columns().iter().map(|value| escape(value)).collect();
+                writeln!(out, "{}", columns.join(separator))?;
+            }
+        }
+        Format::Plain => {
+            for record in &records {
+                writeln!(out, "{}", record.command)?;
+            }
+        }
+        Format::Nul => {
+            for record in &records {
+                write!(out, "{}\0", record.command)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Renders `id: command` rows for `list`, with the descriptions lined up in
+/// a second column. Given the terminal `width`, rows are cut to fit it.
+pub fn render_listing(snippets: &[Snippet], width: Option<usize>, color: bool) -> String {
+    let mut out = String::new();
+    let id_width = snippets.iter().map(|s| s.id.to_string().len()).max().unwrap_or(1);
+    let indent = id_width + 2;
+    let width = width.map(|width| width.max(indent + 10));
+    let described = snippets.iter().any(|s| s.description.is_some());
+
+    // The command column is as wide as the longest first line, but leaves
+    // at least two fifths of the terminal for descriptions, and on narrow
+    // terminals still room for the gap and a character of description.
+    let longest = snippets.iter().map(|s| s.command.lines().next().unwrap_or("").chars().count()).max().unwrap_or(0);
+    let cap = width.map_or(60, |width| ((width - indent) * 3 / 5).max(20).min(width - indent - 3));
+    let column = if described { longest.min(cap) } else { longest };
+
+    for snippet in snippets {
+        let mut lines = snippet.command.lines();
+        let first = lines.next().unwrap_or("");
+        let mut row = format!("{:>id_width$}: ", snippet.id, id_width = id_width);
+        let first = match width {
+            Some(width) => picker::truncate(first, if described { column } else { usize::MAX }.min(width - indent)),
+            None => first.to_string(),
+        };
+        row.push_str(&first);
+
+        if let Some(description) = &snippet.description {
+            row.push_str(&" ".repeat(column.saturating_sub(first.chars().count()) + 2));
+            let room = width.map_or(usize::MAX, |width| width.saturating_sub(indent + column + 2).max(1));
+            let description = picker::truncate(description, room);
+            if color {
+                row.push_str(&format!("\x1b[2m{}\x1b[0m", description));
+            } else {
+                row.push_str(&description);
+            }
+        }
+        out.push_str(row.trim_end());
+        out.push('\n');
+
+        for line in lines {
+            let line = match width {
+                Some(width) => picker::truncate(line, width - indent),
+                None => line.to_string(),
+            };
+            out.push_str(&format!("{}{}\n", " ".repeat(indent), line));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAND: &str = "awk -F, '{print \"a\\tb\"}' in.csv\t| tr , ';'\necho \"done\"";
+
+    fn awkward() -> Snippet {
+        Snippet {
+            id: 7,
+            command: COMMAND.to_string(),
+            description: Some("split, \"quote\"\tand tab".to_string()),
+            tags: vec!["awk".to_string(), "csv".to_string()],
+            created: 1700000000,
+            updated: 1700000100,
+            dangerous: false,
+            last_run: Some(1700000200),
+            last_status: Some(1),
+            last_used: None,
+            use_count: 3,
+        }
+    }
+
+    fn render(format: Format) -> String {
+        let snippet = awkward();
+        let mut out = Vec::new();
+        write(&mut out, &[&snippet], format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn field_order_is_stable() {
+        assert_eq!(
+            FIELDS.join(","),
+            "id,command,description,tags,created,updated,last_used,use_count,last_run,last_status,dangerous"
+        );
+        // JSON records list their keys in the same order.
+        let line = render(Format::Jsonl);
+        let offsets: Vec<usize> = FIELDS.iter().map(|field| line.find(&format!("\"{}\":", field)).unwrap()).collect();
+        assert!(offsets.is_sorted());
+    }
+
+    #[test]
+    fn csv_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("a\tb"), "a\tb");
+    }
+
+    #[test]
+    fn tsv_escapes_separators_and_line_breaks() {
+        assert_eq!(tsv_field("a\tb\nc\r\\d"), "a\\tb\\nc\\r\\\\d");
+        assert_eq!(tsv_field("a,\"b\""), "a,\"b\"");
+    }
+
+    #[test]
+    fn csv_record() {
+        let expected = format!(
+            "{}\n7,\"awk -F, '{{print \"\"a\\tb\"\"}}' in.csv\t| tr , ';'\necho \"\"done\"\"\",\"split, \"\"quote\"\"\tand tab\",awk csv,1700000000,1700000100,,3,1700000200,1,false\n",
+            FIELDS.join(",")
+        );
+        assert_eq!(render(Format::Csv), expected);
+    }
+
+    #[test]
+    fn tsv_record_stays_on_one_line() {
+        let out = render(Format::Tsv);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], FIELDS.join("\t"));
+        let columns: Vec<&str> = lines[1].split('\t').collect();
+        assert_eq!(columns.len(), FIELDS.len());
+        assert_eq!(columns[1], "awk -F, '{print \"a\\\\tb\"}' in.csv\\t| tr , ';'\\necho \"done\"");
+        assert_eq!(columns[2], "split, \"quote\"\\tand tab");
+        assert_eq!(columns[3..], ["awk csv", "1700000000", "1700000100", "", "3", "1700000200", "1", "false"]);
+    }
+
+    #[test]
+    fn json_formats_round_trip_the_command() {
+        let array: serde_json::Value = serde_json::from_str(&render(Format::Json)).unwrap();
+        assert_eq!(array[0]["command"], COMMAND);
+        assert_eq!(array[0]["last_used"], serde_json::Value::Null);
+        assert_eq!(array[0]["tags"], serde_json::json!(["awk", "csv"]));
+
+        let out = render(Format::Jsonl);
+        assert_eq!(out.lines().count(), 1);
+        let record: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(record["command"], COMMAND);
+        assert_eq!(record["description"], "split, \"quote\"\tand tab");
+    }
+
+    #[test]
+    fn plain_and_nul_print_just_the_command() {
+        assert_eq!(render(Format::Plain), format!("{}\n", COMMAND));
+        assert_eq!(render(Format::Nul), format!("{}\0", COMMAND));
+    }
+
+    #[test]
+    fn listing_rows_fit_the_terminal() {
+        let mut long = Snippet::new(12, &"x".repeat(70));
+        long.description = Some("d".repeat(40));
+        let snippets = vec![awkward(), long, Snippet::new(3, "ls")];
+        for width in [10, 15, 20, 25, 30, 40, 80, 120] {
+            for line in render_listing(&snippets, Some(width), false).lines() {
+                assert!(line.chars().count() <= width.max(14), "{:?} is wider than {}", line, width);
+            }
+        }
+    }
+
+    #[test]
+    fn listing_lines_up_descriptions() {
+        let mut described = Snippet::new(10, "du -sh *");
+        described.description = Some("disk usage".to_string());
+        let listing = render_listing(&[Snippet::new(9, "ls -la\ncd -"), described], None, false);
+        assert_eq!(listing, " 9: ls -la\n    cd -\n10: du -sh *  disk usage\n");
+    }
+}
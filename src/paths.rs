@@ -0,0 +1,127 @@
+use crate::config::{Config, Source};
+use crate::error::{Error, Result};
+use crate::oplog;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the store file.
+pub const STORE_ENV: &str = "ONELINERS_FILE";
+
+const APP: &str = "oneliners";
+
+/// Where the store location came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The `store` setting.
+    Setting(Source),
+    /// `~/.oneliners`, used by older releases, because it exists and the
+    /// XDG store doesn't.
+    Legacy,
+    Default,
+}
+
+impl Origin {
+    pub fn describe(&self) -> String {
+        match self {
+            Origin::Setting(source) => source.to_string(),
+            Origin::Legacy => "legacy location".to_string(),
+            Origin::Default => "default".to_string(),
+        }
+    }
+}
+
+/// Every file the tool reads or writes. The lock, operation log and
+/// backups always sit next to the store, since they belong to it.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub store: String,
+    pub origin: Origin,
+    pub config: String,
+    /// Custom safety rules.
+    pub rules: String,
+    /// Recently used placeholder values.
+    pub values: String,
+    /// Cached generator output.
+    pub cache: String,
+}
+
+fn home() -> Result<PathBuf> {
+    dirs::home_dir().ok_or(Error::NoHome)
+}
+
+fn to_string(path: PathBuf) -> Result<String> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|path| Error::Invalid(format!("path {} is not valid UTF-8", Path::new(&path).display())))
+}
+
+/// Our directory under an XDG base directory, such as `$XDG_DATA_HOME`,
+/// or under `fallback` in the home directory when that isn't known.
+fn base_dir(base: Option<PathBuf>, fallback: &str) -> Result<PathBuf> {
+    let base = match base {
+        Some(base) => base,
+        None => home()?.join(fallback),
+    };
+    Ok(base.join(APP))
+}
+
+/// `$XDG_CONFIG_HOME/oneliners/config.toml`.
+pub fn config_file() -> Result<String> {
+    to_string(base_dir(dirs::config_dir(), ".config")?.join("config.toml"))
+}
+
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(path: &str) -> Result<String> {
+    match path.strip_prefix("~/") {
+        Some(rest) => to_string(home()?.join(rest)),
+        None => Ok(path.to_string()),
+    }
+}
+
+impl Paths {
+    /// Works out where everything lives. The store is the `store` setting
+    /// (`--store`, `$ONELINERS_FILE` or a config file), else `~/.oneliners`
+    /// when only that exists, and otherwise
+    /// `$XDG_DATA_HOME/oneliners/snippets`.
+    pub fn resolve(config: &Config) -> Result<Paths> {
+        let (store, origin) = if let (Some(path), Some(setting)) = (config.store()?, config.setting("store")) {
+            (path, Origin::Setting(setting.source.clone()))
+        } else {
+            let default = to_string(base_dir(dirs::data_dir(), ".local/share")?.join("snippets"))?;
+            let legacy = to_string(home()?.join(".oneliners"))?;
+            if !Path::new(&default).exists() && Path::new(&legacy).exists() {
+                (legacy, Origin::Legacy)
+            } else {
+                (default, Origin::Default)
+            }
+        };
+
+        let config_file = config_file()?;
+        if origin == Origin::Legacy {
+            // Keep everything where older releases put it.
+            return Ok(Paths {
+                rules: format!("{}.rules.toml", store),
+                values: format!("{}.vars", store),
+                cache: format!("{}.cache", store),
+                config: config_file,
+                store,
+                origin,
+            });
+        }
+
+        let config_dir = Path::new(&config_file).parent().map(Path::to_path_buf).unwrap_or_default();
+        let state_dir = base_dir(dirs::state_dir().or_else(dirs::data_dir), ".local/state")?;
+        let cache_dir = base_dir(dirs::cache_dir(), ".cache")?;
+        Ok(Paths {
+            rules: to_string(config_dir.join("rules.toml"))?,
+            values: to_string(state_dir.join("values.json"))?,
+            cache: to_string(cache_dir.join("generators.json"))?,
+            config: config_file,
+            store,
+            origin,
+        })
+    }
+
+    pub fn log(&self) -> String {
+        oplog::log_path(&self.store)
+    }
+}
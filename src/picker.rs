@@ -0,0 +1,472 @@
+use crate::search::{self, Match, Mode, Query};
+use crate::snippet::Snippet;
+use crate::terminal::{self, Key, KeyReader, Tty};
+use std::io::{self, Read, Write};
+
+#[derive(Default)]
+pub struct Item {
+    pub text: String,
+    /// Searched along with the text, for a lower score.
+    pub description: Option<String>,
+    /// Extra lines shown in the preview pane under the full text.
+    pub preview: Vec<String>,
+    /// Matched by `tag:<name>` terms in the query.
+    pub tags: Vec<String>,
+}
+
+impl Item {
+    /// A snippet, with its description and tags in the preview.
+    pub fn from_snippet(snippet: &Snippet) -> Item {
+        let mut preview = Vec::new();
+        if let Some(description) = &snippet.description {
+            preview.push(String::new());
+            preview.push(description.clone());
+        }
+        if !snippet.tags.is_empty() {
+            preview.push(String::new());
+            preview.push(format!("tags: {}", snippet.tags.join(", ")));
+        }
+        Item {
+            text: snippet.command.clone(),
+            description: snippet.description.clone(),
+            preview,
+            tags: snippet.tags.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Accept,
+    Edit,
+    Run,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    /// Index into the items the picker was created with.
+    pub index: usize,
+    pub action: Action,
+    /// In multi-select mode, the items marked with Tab in their original
+    /// order, or just `index` if none were. Empty otherwise.
+    pub marked: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    Continue,
+    /// The picker is finished; `None` means it was cancelled.
+    Done(Option<Selection>),
+}
+
+pub struct Picker {
+    items: Vec<Item>,
+    query: String,
+    prompt: String,
+    mode: Mode,
+    actions: bool,
+    multi: bool,
+    color: bool,
+    marked: Vec<bool>,
+    ranked: Vec<(usize, Match)>,
+    selected: usize,
+    scroll: usize,
+}
+
+impl Picker {
+    pub fn new(items: Vec<Item>, query: &str) -> Picker {
+        let marked = vec![false; items.len()];
+        let mut picker = Picker {
+            items,
+            query: query.to_string(),
+            prompt: "> ".to_string(),
+            mode: Mode::Fuzzy,
+            actions: false,
+            multi: false,
+            color: std::env::var_os("NO_COLOR").is_none(),
+            marked,
+            ranked: Vec::new(),
+            selected: 0,
+            scroll: 0,
+        };
+        picker.refilter();
+        picker
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Picker {
+        self.mode = mode;
+        self.refilter();
+        self
+    }
+
+    pub fn prompt(mut self, prompt: &str) -> Picker {
+        self.prompt = prompt.to_string();
+        self
+    }
+
+    /// Offers Ctrl-E (edit) and Ctrl-R (run) in addition to Enter.
+    pub fn actions(mut self, enabled: bool) -> Picker {
+        self.actions = enabled;
+        self
+    }
+
+    /// Lets Tab and Shift-Tab mark several items before Enter.
+    pub fn multi(mut self, enabled: bool) -> Picker {
+        self.multi = enabled;
+        self
+    }
+
+    /// Highlights the matched characters; on unless `NO_COLOR` is set.
+    pub fn color(mut self, enabled: bool) -> Picker {
+        self.color = enabled;
+        self
+    }
+
+    fn refilter(&mut self) {
+        let query = Query::parse(&self.query);
+        let mut ranked: Vec<(usize, Match)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| query.matches_tags(&item.tags))
+            .filter_map(|(i, item)| {
+                search::match_described(&query.text, &item.text, item.description.as_deref(), self.mode).map(|m| (i, m))
+            })
+            .collect();
+        if !self.query.trim().is_empty() {
+            let items = &self.items;
+            ranked.sort_by(|(a, am), (b, bm)| {
+                bm.score.cmp(&am.score).then(items[*a].text.len().cmp(&items[*b].text.len())).then(a.cmp(b))
+            });
+        }
+        self.ranked = ranked;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn finish(&self, action: Action) -> Step {
+        let Some(&(index, _)) = self.ranked.get(self.selected) else {
+            return Step::Continue;
+        };
+        let mut marked = Vec::new();
+        if self.multi {
+            marked = (0..self.items.len()).filter(|&i| self.marked[i]).collect();
+            if marked.is_empty() {
+                marked.push(index);
+            }
+        }
+        Step::Done(Some(Selection { index, action, marked }))
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(&(index, _)) = self.ranked.get(self.selected) {
+            self.marked[index] = !self.marked[index];
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.ranked.is_empty() {
+            return;
+        }
+        let last = self.ranked.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    pub fn handle_key(&mut self, key: Key, page: usize) -> Step {
+        match key {
+            Key::Enter => return self.finish(Action::Accept),
+            Key::Ctrl('e') if self.actions => return self.finish(Action::Edit),
+            Key::Ctrl('r') if self.actions => return self.finish(Action::Run),
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('g') | Key::Ctrl('d') => return Step::Done(None),
+            Key::Tab if self.multi => {
+                self.toggle_mark();
+                self.move_by(1);
+            }
+            Key::BackTab if self.multi => {
+                self.toggle_mark();
+                self.move_by(-1);
+            }
+            Key::Up | Key::Ctrl('p') | Key::Ctrl('k') | Key::BackTab => self.move_by(-1),
+            Key::Down | Key::Ctrl('n') | Key::Tab => self.move_by(1),
+            Key::PageUp => self.move_by(-(page.max(1) as isize)),
+            Key::PageDown => self.move_by(page.max(1) as isize),
+            Key::Home => self.selected = 0,
+            Key::End => self.move_by(isize::MAX / 2),
+            Key::Backspace | Key::Ctrl('h') => {
+                self.query.pop();
+                self.refilter();
+            }
+            Key::Ctrl('u') => {
+                self.query.clear();
+                self.refilter();
+            }
+            Key::Ctrl('w') => {
+                let kept = self.query.trim_end().rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+                self.query.truncate(kept);
+                self.refilter();
+            }
+            Key::Char(c) => {
+                self.query.push(c);
+                self.refilter();
+            }
+            _ => {}
+        }
+        Step::Continue
+    }
+
+    fn layout(height: usize) -> (usize, usize) {
+        // The prompt and help lines take a row each; the preview pane
+        // includes its separator line.
+        let body = height.saturating_sub(2).max(1);
+        let preview = if height >= 12 { (body / 3).max(3) } else { 0 };
+        (body - preview, preview)
+    }
+
+    pub fn render<W: Write>(&mut self, out: &mut W, width: usize, height: usize) -> io::Result<()> {
+        let (list_rows, preview_rows) = Picker::layout(height);
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + list_rows {
+            self.scroll = self.selected + 1 - list_rows;
+        }
+
+        let mut frame = String::from("\x1b[?25l\x1b[H");
+        let mut counter = format!(" {}/{}", self.ranked.len(), self.items.len());
+        let marked = self.marked.iter().filter(|&&m| m).count();
+        if marked > 0 {
+            counter.push_str(&format!(" ({})", marked));
+        }
+        let prompt_line = format!("{}{}", self.prompt, self.query);
+        let room = width.saturating_sub(counter.len());
+        frame.push_str(&truncate(&prompt_line, room));
+        frame.push_str(&" ".repeat(room.saturating_sub(prompt_line.chars().count())));
+        frame.push_str(&counter);
+        frame.push_str("\x1b[K\r\n");
+
+        for row in 0..list_rows {
+            if let Some((index, m)) = self.ranked.get(self.scroll + row) {
+                let current = self.scroll + row == self.selected;
+                let text = truncate(&self.items[*index].text, width.saturating_sub(2));
+                frame.push(if current { '>' } else { ' ' });
+                frame.push(if self.marked[*index] { '*' } else { ' ' });
+                if self.color {
+                    frame.push_str(&search::highlight(&text, &m.positions));
+                } else {
+                    frame.push_str(&text);
+                }
+            }
+            frame.push_str("\x1b[K\r\n");
+        }
+
+        if preview_rows > 0 {
+            frame.push_str(&"─".repeat(width));
+            frame.push_str("\r\n");
+            let preview: Vec<String> = match self.ranked.get(self.selected) {
+                Some((index, _)) => {
+                    let item = &self.items[*index];
+                    item.text.lines().map(str::to_string).chain(item.preview.iter().cloned()).collect()
+                }
+                None => Vec::new(),
+            };
+            for row in 0..preview_rows - 1 {
+                if let Some(line) = preview.get(row) {
+                    frame.push_str(&truncate(line, width));
+                }
+                frame.push_str("\x1b[K\r\n");
+            }
+        }
+
+        let help = if self.actions {
+            "enter copy  ^E edit  ^R run  esc cancel"
+        } else if self.multi {
+            "tab mark  enter select  esc cancel"
+        } else {
+            "enter select  esc cancel"
+        };
+        frame.push_str(&truncate(help, width));
+        frame.push_str("\x1b[K\x1b[J");
+
+        let cursor = (self.prompt.chars().count() + self.query.chars().count()).min(width.saturating_sub(1));
+        frame.push_str(&format!("\x1b[1;{}H\x1b[?25h", cursor + 1));
+
+        out.write_all(frame.as_bytes())?;
+        out.flush()
+    }
+
+    /// Drives the picker from `keys`, redrawing to `out` after every key.
+    /// Returns `None` if the user cancels or the input runs out.
+    pub fn run<R: Read, W: Write>(
+        mut self,
+        keys: &mut KeyReader<R>,
+        out: &mut W,
+        size: impl Fn() -> (usize, usize),
+    ) -> io::Result<Option<Selection>> {
+        loop {
+            let (width, height) = size();
+            self.render(out, width, height)?;
+            let Some(key) = keys.next_key()? else {
+                return Ok(None);
+            };
+            if let Step::Done(selection) = self.handle_key(key, Picker::layout(height).0) {
+                return Ok(selection);
+            }
+        }
+    }
+}
+
+/// Fits `text` on one row. Line breaks are shown as `⏎` so match
+/// positions still line up with the chars of the original text.
+pub fn truncate(text: &str, width: usize) -> String {
+    let flat: String = text
+        .chars()
+        .map(|c| match c {
+            '\n' => '⏎',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    if flat.chars().count() <= width {
+        return flat;
+    }
+    let mut cut: String = flat.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Runs the picker full-screen on the controlling terminal.
+pub fn pick(picker: Picker) -> io::Result<Option<Selection>> {
+    let mut tty = Tty::open()?;
+    let mut keys = KeyReader::new(tty.try_clone()?);
+    let sizer = tty.try_clone()?;
+    // Re-query the size on every frame so resizes are picked up.
+    picker.run(&mut keys, &mut tty, || terminal::size(&sizer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<Item> {
+        ["git status", "git stash pop", "ls -la", "docker ps"]
+            .iter()
+            .map(|text| Item { text: text.to_string(), ..Default::default() })
+            .collect()
+    }
+
+    fn press(picker: &mut Picker, keys: &[Key]) -> Step {
+        let mut step = Step::Continue;
+        for &key in keys {
+            step = picker.handle_key(key, 2);
+            if step != Step::Continue {
+                break;
+            }
+        }
+        step
+    }
+
+    fn selected(index: usize, action: Action) -> Step {
+        Step::Done(Some(Selection { index, action, marked: Vec::new() }))
+    }
+
+    #[test]
+    fn typing_narrows_the_list_and_enter_picks_the_best_match() {
+        let mut picker = Picker::new(items(), "");
+        let keys: Vec<Key> = "stash".chars().map(Key::Char).chain([Key::Enter]).collect();
+        assert_eq!(press(&mut picker, &keys), selected(1, Action::Accept));
+    }
+
+    #[test]
+    fn editing_the_query_refilters() {
+        let mut picker = Picker::new(items(), "dock");
+        assert_eq!(press(&mut picker, &[Key::Ctrl('u'), Key::Char('l'), Key::Char('s'), Key::Enter]), selected(2, Action::Accept));
+
+        let mut picker = Picker::new(items(), "git lsx");
+        assert_eq!(press(&mut picker, &[Key::Enter]), Step::Continue);
+        assert_eq!(press(&mut picker, &[Key::Backspace, Key::Ctrl('w'), Key::Enter]), selected(0, Action::Accept));
+    }
+
+    #[test]
+    fn arrows_move_the_selection_within_bounds() {
+        let mut picker = Picker::new(items(), "");
+        assert_eq!(press(&mut picker, &[Key::Down, Key::Down, Key::Up, Key::Enter]), selected(1, Action::Accept));
+
+        let mut picker = Picker::new(items(), "");
+        assert_eq!(press(&mut picker, &[Key::Up, Key::Enter]), selected(0, Action::Accept));
+
+        let mut picker = Picker::new(items(), "");
+        assert_eq!(press(&mut picker, &[Key::End, Key::Down, Key::Enter]), selected(3, Action::Accept));
+
+        let mut picker = Picker::new(items(), "");
+        assert_eq!(press(&mut picker, &[Key::PageDown, Key::Home, Key::Ctrl('n'), Key::Enter]), selected(1, Action::Accept));
+    }
+
+    #[test]
+    fn actions_are_only_offered_when_enabled() {
+        let mut picker = Picker::new(items(), "ls").actions(true);
+        assert_eq!(press(&mut picker, &[Key::Ctrl('e')]), selected(2, Action::Edit));
+        let mut picker = Picker::new(items(), "ls").actions(true);
+        assert_eq!(press(&mut picker, &[Key::Ctrl('r')]), selected(2, Action::Run));
+
+        let mut picker = Picker::new(items(), "ls");
+        assert_eq!(press(&mut picker, &[Key::Ctrl('e'), Key::Ctrl('r'), Key::Esc]), Step::Done(None));
+    }
+
+    #[test]
+    fn enter_does_nothing_without_matches() {
+        let mut picker = Picker::new(items(), "zzz");
+        assert_eq!(press(&mut picker, &[Key::Enter]), Step::Continue);
+        assert_eq!(press(&mut picker, &[Key::Ctrl('c')]), Step::Done(None));
+    }
+
+    #[test]
+    fn tab_marks_several_items_in_multi_mode() {
+        let mut picker = Picker::new(items(), "").multi(true);
+        let step = press(&mut picker, &[Key::Tab, Key::Down, Key::Tab, Key::Up, Key::Up, Key::Enter]);
+        assert_eq!(step, Step::Done(Some(Selection { index: 1, action: Action::Accept, marked: vec![0, 2] })));
+
+        // With every mark taken back, Enter picks just the current item.
+        let mut picker = Picker::new(items(), "").multi(true);
+        let step = press(&mut picker, &[Key::Tab, Key::Up, Key::Tab, Key::Enter]);
+        assert_eq!(step, Step::Done(Some(Selection { index: 1, action: Action::Accept, marked: vec![1] })));
+    }
+
+    #[test]
+    fn run_reads_keys_and_redraws_until_done() {
+        let mut keys = KeyReader::new(&b"pop\x1b[A\r"[..]);
+        let mut out = Vec::new();
+        let selection = Picker::new(items(), "").actions(true).run(&mut keys, &mut out, || (40, 10)).unwrap();
+        assert_eq!(selection, Some(Selection { index: 1, action: Action::Accept, marked: Vec::new() }));
+        let frames = String::from_utf8(out).unwrap();
+        assert_eq!(frames.matches("\x1b[?25l\x1b[H").count(), 5);
+        assert!(frames.contains("> pop"));
+    }
+
+    #[test]
+    fn run_gives_up_when_the_input_ends() {
+        let mut keys = KeyReader::new(&b"git\x05"[..]);
+        let selection = Picker::new(items(), "").run(&mut keys, &mut Vec::new(), || (80, 24)).unwrap();
+        assert_eq!(selection, None);
+
+        let mut keys = KeyReader::new(&b"\x1b"[..]);
+        assert_eq!(Picker::new(items(), "").run(&mut keys, &mut Vec::new(), || (80, 24)).unwrap(), None);
+    }
+
+    #[test]
+    fn highlights_follow_the_color_setting() {
+        let mut out = Vec::new();
+        Picker::new(items(), "ls").color(true).render(&mut out, 40, 10).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\x1b[1;33mls\x1b[0m"));
+
+        let mut out = Vec::new();
+        Picker::new(items(), "ls").color(false).render(&mut out, 40, 10).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("\x1b[1;33m"));
+    }
+
+    #[test]
+    fn truncate_flattens_line_breaks() {
+        assert_eq!(truncate("a\nb\tc", 10), "a⏎b c");
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("äöü", 3), "äöü");
+    }
+}
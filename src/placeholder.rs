@@ -0,0 +1,477 @@
+use crate::error::{Error, Result};
+use crate::generator;
+use crate::store;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+/// How many values are remembered per placeholder name.
+const RECENT_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+    /// Shell command from `<name:$(command)>` whose output lines are
+    /// offered as choices.
+    pub generator: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Length in bytes of the `$( ... )` at the start of `s`, honouring nested
+/// parentheses and quotes.
+fn command_substitution_len(s: &str) -> Option<usize> {
+    let body = s.strip_prefix("$(")?;
+    let mut depth = 1;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(2 + i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Tries to read `<name>`, `<name:default>` or `<name:$(command)>` at the
+/// start of `s`, returning the placeholder and its length in bytes.
+fn parse_placeholder(s: &str) -> Option<(Placeholder, usize)> {
+    let rest = s.strip_prefix('<')?;
+    if !rest.starts_with(is_name_start) {
+        return None;
+    }
+    let name_len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    let after = &rest[name_len..];
+
+    if after.starts_with('>') {
+        let placeholder = Placeholder { name: name.to_string(), default: None, generator: None };
+        return Some((placeholder, 1 + name_len + 1));
+    }
+
+    let value = after.strip_prefix(':')?;
+    if let Some(len) = command_substitution_len(value)
+        && value[len..].starts_with('>')
+    {
+        let placeholder = Placeholder {
+            name: name.to_string(),
+            default: None,
+            generator: Some(value[2..len - 1].trim().to_string()),
+        };
+        return Some((placeholder, 1 + name_len + 1 + len + 1));
+    }
+
+    let end = value.find(['>', '\n'])?;
+    if !value[end..].starts_with('>') {
+        return None;
+    }
+    let placeholder = Placeholder {
+        name: name.to_string(),
+        default: Some(value[..end].to_string()),
+        generator: None,
+    };
+    Some((placeholder, 1 + name_len + 1 + end + 1))
+}
+
+/// Splits `command` into text and placeholders. A backslash in front of
+/// something that reads as a placeholder makes it plain text, so `\<b>`
+/// stays `<b>` in the command.
+fn parse(command: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < command.len() {
+        if let Some(escaped) = command[i..].strip_prefix('\\')
+            && let Some((_, len)) = parse_placeholder(escaped)
+        {
+            text.push_str(&escaped[..len]);
+            i += 1 + len;
+        } else if let Some((placeholder, len)) = parse_placeholder(&command[i..]) {
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            i += len;
+        } else {
+            let c = command[i..].chars().next().unwrap_or_default();
+            text.push(c);
+            i += c.len_utf8();
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
+
+/// The placeholders in `command`, in order of first appearance. When a name
+/// repeats, the first default given for it wins.
+pub fn placeholders(command: &str) -> Vec<Placeholder> {
+    let mut found: Vec<Placeholder> = Vec::new();
+    for segment in parse(command) {
+        if let Segment::Placeholder(placeholder) = segment {
+            match found.iter_mut().find(|p| p.name == placeholder.name) {
+                Some(existing) => {
+                    if existing.default.is_none() && existing.generator.is_none() {
+                        existing.default = placeholder.default;
+                        existing.generator = placeholder.generator;
+                    }
+                }
+                None => found.push(placeholder),
+            }
+        }
+    }
+    found
+}
+
+/// Substitutes every placeholder that has a value. Placeholders without
+/// one are left as written; escaped ones lose their backslash.
+pub fn expand(command: &str, values: &HashMap<String, String>) -> String {
+    parse(command)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text,
+            Segment::Placeholder(p) => match values.get(&p.name) {
+                Some(value) => value.clone(),
+                None => match (p.default, p.generator) {
+                    (Some(default), _) => format!("<{}:{}>", p.name, default),
+                    (None, Some(generator)) => format!("<{}:$({})>", p.name, generator),
+                    (None, None) => format!("<{}>", p.name),
+                },
+            },
+        })
+        .collect()
+}
+
+/// Parses `--var name=value` arguments.
+pub fn parse_vars(vars: &[String]) -> Result<HashMap<String, String>> {
+    vars.iter()
+        .map(|var| match var.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => Err(Error::Invalid(format!("invalid --var '{}', expected NAME=VALUE", var))),
+        })
+        .collect()
+}
+
+/// Recently used values per placeholder name, most recent first, kept in a
+/// JSON file next to the store.
+pub struct RecentValues {
+    path: String,
+    values: HashMap<String, Vec<String>>,
+}
+
+impl RecentValues {
+    pub fn load(path: &str) -> RecentValues {
+        let values = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        RecentValues { path: path.to_string(), values }
+    }
+
+    pub fn get(&self, name: &str) -> &[String] {
+        self.values.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn remember(&mut self, name: &str, value: &str) {
+        let recent = self.values.entry(name.to_string()).or_default();
+        recent.retain(|v| v != value);
+        recent.insert(0, value.to_string());
+        recent.truncate(RECENT_LIMIT);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        store::write_atomic(&self.path, serde_json::to_string_pretty(&self.values)?.as_bytes())
+    }
+}
+
+/// Whoever fills in placeholders at a terminal. `fill` works out what to
+/// ask; an implementation does the asking.
+pub trait Prompt {
+    /// Whether generator `command` may run for `placeholder`. Only asked
+    /// when there is no cached output for it.
+    fn approve(&mut self, placeholder: &Placeholder, command: &str) -> bool;
+
+    /// Generator `command` gave nothing to choose from: it printed nothing,
+    /// or it failed with `error`. The value is asked for instead.
+    fn no_choices(&mut self, command: &str, error: Option<&Error>);
+
+    /// Lets the user pick one of `choices` for `placeholder`, returning its
+    /// index, or `None` if they cancelled.
+    fn choose(&mut self, placeholder: &Placeholder, choices: &[String]) -> Result<Option<usize>>;
+
+    /// Asks for a value for `placeholder`; an empty answer takes
+    /// `suggestion`. `recent` are values used before, most recent first.
+    /// Returns `None` at the end of input.
+    fn ask(&mut self, placeholder: &Placeholder, suggestion: Option<&str>, recent: &[String]) -> Result<Option<String>>;
+}
+
+/// Offers a generator's output lines to pick from. Returns `None` if the
+/// generator produced nothing usable or wasn't approved, so the caller can
+/// ask for the value instead.
+fn choose_generated(
+    placeholder: &Placeholder,
+    generator: &str,
+    cache: &mut generator::Cache,
+    prompt: &mut dyn Prompt,
+) -> Result<Option<String>> {
+    if !cache.is_cached(generator) && !prompt.approve(placeholder, generator) {
+        return Ok(None);
+    }
+    let choices = match cache.lines(generator, generator::DEFAULT_TIMEOUT) {
+        Ok(choices) if !choices.is_empty() => choices,
+        Ok(_) => {
+            prompt.no_choices(generator, None);
+            return Ok(None);
+        }
+        Err(e) => {
+            prompt.no_choices(generator, Some(&e));
+            return Ok(None);
+        }
+    };
+    match prompt.choose(placeholder, &choices)? {
+        Some(index) => Ok(Some(choices[index].clone())),
+        None => Err(Error::Aborted),
+    }
+}
+
+/// Works out a value for every placeholder in `command` and returns the
+/// expanded command. Values come from `given` first. With a `prompt`,
+/// generator placeholders offer their command's output to pick from and the
+/// rest are asked for, suggesting the most recent value or the declared
+/// default. Without one, the declared default is used. Cached generator
+/// output is reused without asking to run the generator again.
+pub fn fill(
+    command: &str,
+    given: &HashMap<String, String>,
+    recent: &mut RecentValues,
+    cache: &mut generator::Cache,
+    mut prompt: Option<&mut dyn Prompt>,
+) -> Result<String> {
+    let mut values = HashMap::new();
+    for placeholder in placeholders(command) {
+        let generated = match (&placeholder.generator, given.contains_key(&placeholder.name), prompt.as_deref_mut()) {
+            (Some(generator), false, Some(prompt)) => choose_generated(&placeholder, generator, cache, prompt)?,
+            _ => None,
+        };
+
+        let value = match (given.get(&placeholder.name).cloned().or(generated), prompt.as_deref_mut()) {
+            (Some(value), _) => value,
+            (None, Some(prompt)) => {
+                let history = recent.get(&placeholder.name).to_vec();
+                let suggestion = history.first().map(String::as_str).or(placeholder.default.as_deref());
+                match prompt.ask(&placeholder, suggestion, &history)? {
+                    Some(value) => value,
+                    None => return Err(Error::Aborted),
+                }
+            }
+            (None, None) => match &placeholder.default {
+                Some(default) => default.clone(),
+                None => {
+                    return Err(Error::Invalid(format!(
+                        "no value for <{}>; pass --var {}=VALUE",
+                        placeholder.name, placeholder.name
+                    )));
+                }
+            },
+        };
+        recent.remember(&placeholder.name, &value);
+        values.insert(placeholder.name, value);
+    }
+    Ok(expand(command, &values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholder(name: &str, default: Option<&str>, generator: Option<&str>) -> Placeholder {
+        Placeholder { name: name.to_string(), default: default.map(str::to_string), generator: generator.map(str::to_string) }
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_names_defaults_and_generators() {
+        assert_eq!(parse_placeholder("<host> rest"), Some((placeholder("host", None, None), 6)));
+        assert_eq!(parse_placeholder("<port:22>"), Some((placeholder("port", Some("22"), None), 9)));
+        assert_eq!(parse_placeholder("<dir:>"), Some((placeholder("dir", Some(""), None), 6)));
+        assert_eq!(
+            parse_placeholder("<pod:$(kubectl get pods -o name | grep -v 'x)')>"),
+            Some((placeholder("pod", None, Some("kubectl get pods -o name | grep -v 'x)'")), 48))
+        );
+        assert_eq!(
+            parse_placeholder("<f:$(ls $(dirname \"$(pwd)\"))>"),
+            Some((placeholder("f", None, Some("ls $(dirname \"$(pwd)\")")), 29))
+        );
+    }
+
+    #[test]
+    fn leaves_things_that_are_not_placeholders() {
+        assert_eq!(parse_placeholder("<host"), None);
+        assert_eq!(parse_placeholder("<port:22"), None);
+        assert_eq!(parse_placeholder("<port:2\n2>"), None);
+        assert_eq!(parse_placeholder("<1st>"), None);
+        assert_eq!(parse_placeholder("< host>"), None);
+        assert!(placeholders("cat < in > out; cat <<EOF\nhi\nEOF").is_empty());
+        assert!(placeholders("grep '\\<word\\>' notes").is_empty());
+    }
+
+    #[test]
+    fn repeated_names_are_asked_once_with_the_first_default() {
+        let found = placeholders("cp <file> <file:a.txt>.bak; echo <file:b.txt> <dir>");
+        assert_eq!(found, [placeholder("file", Some("a.txt"), None), placeholder("dir", None, None)]);
+    }
+
+    #[test]
+    fn expands_known_values_and_keeps_the_rest() {
+        let command = "ssh <user:root>@<host> -p <port:22> <pod:$(kubectl get pods)>";
+        assert_eq!(
+            expand(command, &values(&[("host", "web1"), ("user", "me")])),
+            "ssh me@web1 -p <port:22> <pod:$(kubectl get pods)>"
+        );
+        assert_eq!(expand("echo <a> <a>", &values(&[("a", "x")])), "echo x x");
+    }
+
+    #[test]
+    fn escaped_placeholders_are_plain_text() {
+        let command = "curl -d '\\<user>bob</user>' <url>";
+        assert_eq!(placeholders(command), [placeholder("url", None, None)]);
+        assert_eq!(expand(command, &values(&[("url", "http://x")])), "curl -d '<user>bob</user>' http://x");
+        assert_eq!(expand("echo \\<name:x>", &HashMap::new()), "echo <name:x>");
+        assert_eq!(expand("echo \\\\<name>", &values(&[("name", "x")])), "echo \\<name>");
+    }
+
+    #[test]
+    fn parses_vars() {
+        let vars = vec!["host=web1".to_string(), "query=a=b".to_string(), "empty=".to_string()];
+        assert_eq!(parse_vars(&vars).unwrap(), values(&[("host", "web1"), ("query", "a=b"), ("empty", "")]));
+        for bad in ["host", "=web1"] {
+            let error = parse_vars(&[bad.to_string()]).unwrap_err();
+            assert_eq!(error.to_string(), format!("invalid --var '{}', expected NAME=VALUE", bad));
+        }
+    }
+
+    #[test]
+    fn fills_without_a_terminal_from_vars_and_defaults() {
+        let dir = std::env::temp_dir().join(format!("oneliners-placeholder-{}", std::process::id()));
+        let mut recent = RecentValues::load(dir.join("values.json").to_str().unwrap());
+        let mut cache = generator::Cache::load(dir.join("cache.json").to_str().unwrap(), false);
+
+        let command = "ssh <user:root>@<host> -p <port:22>";
+        let filled = fill(command, &values(&[("host", "web1")]), &mut recent, &mut cache, None);
+        assert_eq!(filled.unwrap(), "ssh root@web1 -p 22");
+        assert_eq!(recent.get("host"), ["web1"]);
+
+        for command in ["ssh <host>", "kubectl logs <pod:$(kubectl get pods)>"] {
+            let error = fill(command, &HashMap::new(), &mut recent, &mut cache, None).unwrap_err();
+            assert!(error.to_string().starts_with("no value for <"), "{}", error);
+        }
+        let filled = fill("kubectl logs <pod:$(kubectl get pods)>", &values(&[("pod", "web")]), &mut recent, &mut cache, None);
+        assert_eq!(filled.unwrap(), "kubectl logs web");
+        assert!(!dir.exists());
+    }
+
+    /// Answers with canned values and notes what it was asked.
+    #[derive(Default)]
+    struct Scripted {
+        approve: bool,
+        answers: Vec<&'static str>,
+        asked: Vec<String>,
+    }
+
+    impl Prompt for Scripted {
+        fn approve(&mut self, placeholder: &Placeholder, command: &str) -> bool {
+            self.asked.push(format!("approve <{}> {}", placeholder.name, command));
+            self.approve
+        }
+
+        fn no_choices(&mut self, command: &str, error: Option<&Error>) {
+            self.asked.push(format!("no choices from {}: {:?}", command, error.map(Error::to_string)));
+        }
+
+        fn choose(&mut self, placeholder: &Placeholder, choices: &[String]) -> Result<Option<usize>> {
+            self.asked.push(format!("choose <{}> from {}", placeholder.name, choices.join(",")));
+            Ok(Some(choices.len() - 1))
+        }
+
+        fn ask(&mut self, placeholder: &Placeholder, suggestion: Option<&str>, recent: &[String]) -> Result<Option<String>> {
+            self.asked.push(format!("ask <{}> {:?} {:?}", placeholder.name, suggestion, recent));
+            Ok((!self.answers.is_empty()).then(|| self.answers.remove(0).to_string()))
+        }
+    }
+
+    #[test]
+    fn asks_the_prompt_for_what_is_missing() {
+        let dir = std::env::temp_dir().join(format!("oneliners-placeholder-prompt-{}", std::process::id()));
+        let mut recent = RecentValues::load(dir.join("values.json").to_str().unwrap());
+        recent.remember("user", "admin");
+        let mut cache = generator::Cache::load(dir.join("cache.json").to_str().unwrap(), false);
+        let command = "ssh <user:root>@<host:$(printf 'a\\nb\\n')> <port:$(true)>";
+
+        let mut prompt = Scripted { approve: true, answers: vec!["2222"], ..Default::default() };
+        let filled = fill(command, &values(&[("user", "me")]), &mut recent, &mut cache, Some(&mut prompt));
+        assert_eq!(filled.unwrap(), "ssh me@b 2222");
+        assert_eq!(
+            prompt.asked,
+            [
+                "approve <host> printf 'a\\nb\\n'",
+                "choose <host> from a,b",
+                "approve <port> true",
+                "no choices from true: None",
+                "ask <port> None []",
+            ]
+        );
+
+        // Cached output is offered without asking again; a generator turned
+        // down falls back to asking.
+        let mut prompt = Scripted { answers: vec!["x", "tmp"], ..Default::default() };
+        let command = "ssh <user:root>@<host:$(printf 'a\\nb\\n')> <dir:$(false)>";
+        let filled = fill(command, &HashMap::new(), &mut recent, &mut cache, Some(&mut prompt));
+        assert_eq!(filled.unwrap(), "ssh x@b tmp");
+        assert_eq!(
+            prompt.asked,
+            [
+                "ask <user> Some(\"me\") [\"me\", \"admin\"]",
+                "choose <host> from a,b",
+                "approve <dir> false",
+                "ask <dir> None []",
+            ]
+        );
+
+        let error = fill("ssh <host>", &HashMap::new(), &mut recent, &mut cache, Some(&mut prompt)).unwrap_err();
+        assert!(matches!(error, Error::Aborted));
+    }
+}
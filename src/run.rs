@@ -0,0 +1,23 @@
+use std::env;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+
+/// The shell snippets run in: `preference` (the `shell` setting), then
+/// `$SHELL`, then `sh`.
+pub fn shell(preference: Option<&str>) -> String {
+    preference
+        .map(str::to_string)
+        .or_else(|| env::var("SHELL").ok())
+        .filter(|shell| !shell.trim().is_empty())
+        .unwrap_or_else(|| "sh".to_string())
+}
+
+/// Runs `command` with `shell -c`, sharing our stdin, stdout and stderr,
+/// and returns its exit code. Multi-line snippets are passed whole, so the
+/// shell runs them as a script body. Death by signal maps to 128 + the signal
+/// number, as shells report it.
+pub fn execute(command: &str, shell: &str) -> io::Result<i32> {
+    let status = Command::new(shell).arg("-c").arg(command).status()?;
+    Ok(status.code().or_else(|| status.signal().map(|signal| 128 + signal)).unwrap_or(1))
+}
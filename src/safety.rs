@@ -0,0 +1,646 @@
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+
+/// A simple command: program and arguments, with quotes removed.
+pub type Words = Vec<String>;
+
+/// Splits shell source into simple commands. Quotes and escapes are
+/// resolved, `;`, `&`, `|`, newlines and parentheses separate commands, and
+/// the bodies of `$(...)` and backquotes are split as commands of their
+/// own. This is not a full shell parser, but it does not mistake quoted
+/// text or comments for commands.
+pub fn split_commands(source: &str) -> Vec<Words> {
+    let mut splitter = Splitter::default();
+    splitter.split(source);
+    splitter.end_command();
+    splitter.commands
+}
+
+#[derive(Default)]
+struct Splitter {
+    commands: Vec<Words>,
+    current: Words,
+    word: String,
+    /// Set once the current word has started, even if it is still empty
+    /// (as with `''`).
+    in_word: bool,
+    /// Here-document delimiters whose bodies start after the next newline,
+    /// and whether leading tabs are stripped (`<<-`).
+    heredocs: Vec<(String, bool)>,
+}
+
+impl Splitter {
+    fn push(&mut self, c: char) {
+        self.word.push(c);
+        self.in_word = true;
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word {
+            self.current.push(std::mem::take(&mut self.word));
+            self.in_word = false;
+        }
+    }
+
+    fn end_command(&mut self) {
+        self.end_word();
+        if !self.current.is_empty() {
+            self.commands.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    /// Handles a `$(...)` or backquoted command starting at `chars[i]`,
+    /// keeping its text in the current word. Returns the index of its last
+    /// char.
+    fn substitution(&mut self, chars: &[char], i: usize) -> usize {
+        let (inner, end) = if chars[i] == '`' {
+            let len = chars[i + 1..].iter().position(|&c| c == '`').unwrap_or(chars.len() - i - 1);
+            (chars[i + 1..i + 1 + len].iter().collect(), i + 1 + len)
+        } else {
+            let (inner, len) = substitution_body(&chars[i + 2..]);
+            (inner, i + 1 + len)
+        };
+        self.commands.extend(split_commands(&inner));
+        for &c in &chars[i..=end.min(chars.len() - 1)] {
+            self.push(c);
+        }
+        end
+    }
+
+    /// Reads the delimiter of a here-document whose `<<` ends at `chars[i]`
+    /// and returns the index of the delimiter's last char.
+    fn heredoc(&mut self, chars: &[char], mut i: usize) -> usize {
+        let strip_tabs = chars.get(i + 1) == Some(&'-');
+        if strip_tabs {
+            i += 1;
+        }
+        while chars.get(i + 1).is_some_and(|c| *c == ' ' || *c == '\t') {
+            i += 1;
+        }
+        let mut delimiter = String::new();
+        while let Some(&c) = chars.get(i + 1) {
+            if c.is_whitespace() || ";&|()<>".contains(c) {
+                break;
+            }
+            if c != '\'' && c != '"' && c != '\\' {
+                delimiter.push(c);
+            }
+            i += 1;
+        }
+        if !delimiter.is_empty() {
+            self.heredocs.push((delimiter, strip_tabs));
+        }
+        i
+    }
+
+    /// Skips the bodies of pending here-documents, which start after the
+    /// newline at `chars[i]`. Returns the index of the last char skipped.
+    fn skip_heredoc_bodies(&mut self, chars: &[char], mut i: usize) -> usize {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            loop {
+                let start = i + 1;
+                if start >= chars.len() {
+                    return chars.len();
+                }
+                let end = chars[start..].iter().position(|&c| c == '\n').map_or(chars.len(), |p| start + p);
+                let line: String = chars[start..end].iter().collect();
+                i = end;
+                let line = if strip_tabs { line.trim_start_matches('\t') } else { &line };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+        i
+    }
+
+    fn split(&mut self, source: &str) {
+        let chars: Vec<char> = source.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => {
+                    if let Some(&next) = chars.get(i + 1) {
+                        if next != '\n' {
+                            self.push(next);
+                        }
+                        i += 1;
+                    }
+                }
+                '\'' => {
+                    self.in_word = true;
+                    i += 1;
+                    while i < chars.len() && chars[i] != '\'' {
+                        self.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                '"' => {
+                    self.in_word = true;
+                    i += 1;
+                    while i < chars.len() && chars[i] != '"' {
+                        if chars[i] == '\\' && matches!(chars.get(i + 1), Some('"' | '\\' | '$' | '`')) {
+                            i += 1;
+                            self.push(chars[i]);
+                        } else if chars[i] == '`' || (chars[i] == '$' && chars.get(i + 1) == Some(&'(')) {
+                            i = self.substitution(&chars, i);
+                        } else {
+                            self.push(chars[i]);
+                        }
+                        i += 1;
+                    }
+                }
+                '$' if chars.get(i + 1) == Some(&'(') => i = self.substitution(&chars, i),
+                '`' => i = self.substitution(&chars, i),
+                '#' if !self.in_word => {
+                    while i + 1 < chars.len() && chars[i + 1] != '\n' {
+                        i += 1;
+                    }
+                }
+                '\n' => {
+                    self.end_command();
+                    i = self.skip_heredoc_bodies(&chars, i);
+                }
+                ';' | '&' | '|' | '(' | ')' => self.end_command(),
+                // A `<<<` here-string is an ordinary redirection; only `<<`
+                // starts a here-document.
+                '<' if chars.get(i + 1) == Some(&'<') && chars.get(i + 2) == Some(&'<') => {
+                    self.end_word();
+                    i += 2;
+                }
+                '<' if chars.get(i + 1) == Some(&'<') => {
+                    self.end_word();
+                    i = self.heredoc(&chars, i + 1);
+                }
+                '<' | '>' => self.end_word(),
+                c if c.is_whitespace() => self.end_word(),
+                c => self.push(c),
+            }
+            i += 1;
+        }
+    }
+}
+
+/// The body of a `$(...)` whose opening has already been consumed, and the
+/// number of chars up to and including the closing parenthesis.
+fn substitution_body(chars: &[char]) -> (String, usize) {
+    let mut depth = 1;
+    let mut quote = None;
+    for (i, &c) in chars.iter().enumerate() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return (chars[..i].iter().collect(), i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    (chars.iter().collect(), chars.len())
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+fn basename(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+/// Options of wrapper commands that take a separate value.
+fn wrapper_option_takes_value(wrapper: &str, option: &str) -> bool {
+    match wrapper {
+        "sudo" | "doas" => matches!(option, "-u" | "-g" | "-C" | "-h" | "-p" | "-r" | "-t" | "-U" | "-D"),
+        "nice" => option == "-n",
+        "xargs" => matches!(option, "-I" | "-L" | "-n" | "-P" | "-d" | "-E" | "-s" | "-a"),
+        "timeout" => matches!(option, "-s" | "-k" | "--signal" | "--kill-after"),
+        _ => false,
+    }
+}
+
+/// The command string a shell is given with `-c`, which may be combined
+/// with other short options (`-lc`, `-ec`). It is the first argument after
+/// the options.
+fn shell_command_string(args: &[String]) -> Option<&str> {
+    let mut command = false;
+    let mut i = 0;
+    while let Some(word) = args.get(i) {
+        if word == "--" {
+            i += 1;
+            break;
+        } else if word.starts_with("--") {
+            // Long options such as --norc or --login take no value.
+        } else if let Some(flags) = word.strip_prefix('-').or_else(|| word.strip_prefix('+')).filter(|f| !f.is_empty()) {
+            command |= word.starts_with('-') && flags.contains('c');
+            // `-o name` and `+o name` set a shell option.
+            if flags.ends_with('o') {
+                i += 1;
+            }
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    if command { args.get(i).map(String::as_str) } else { None }
+}
+
+/// Global options of `git` that take a separate value.
+fn git_option_takes_value(option: &str) -> bool {
+    matches!(option, "-C" | "-c" | "--git-dir" | "--work-tree" | "--namespace" | "--config-env" | "--exec-path")
+}
+
+/// Strips variable assignments and wrappers such as `sudo`, `env` or
+/// `xargs` so the first word is the program that actually runs. Commands
+/// handed to `sh -c` or `eval` are split and returned as well.
+fn effective_commands(words: &[String]) -> Vec<Words> {
+    let mut rest = words;
+    loop {
+        let Some(first) = rest.first() else {
+            return Vec::new();
+        };
+        let program = basename(first);
+        if is_assignment(first) || matches!(first.as_str(), "{" | "}" | "!" | "then" | "do" | "else" | "if" | "while" | "until") {
+            rest = &rest[1..];
+            continue;
+        }
+        match program {
+            "sudo" | "doas" | "env" | "nohup" | "nice" | "time" | "exec" | "command" | "builtin" | "xargs" | "timeout" => {
+                rest = &rest[1..];
+                while let Some(word) = rest.first() {
+                    if word == "--" {
+                        rest = &rest[1..];
+                        break;
+                    } else if word.starts_with('-') {
+                        let takes_value = wrapper_option_takes_value(program, word);
+                        rest = &rest[if takes_value { 2 } else { 1 }.min(rest.len())..];
+                    } else if program == "env" && is_assignment(word) {
+                        rest = &rest[1..];
+                    } else {
+                        break;
+                    }
+                }
+                if program == "timeout" && !rest.is_empty() {
+                    rest = &rest[1..];
+                }
+            }
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => {
+                return match shell_command_string(&rest[1..]) {
+                    Some(source) => split_commands(source).iter().flat_map(|c| effective_commands(c)).collect(),
+                    None => vec![rest.to_vec()],
+                };
+            }
+            "eval" => {
+                let source = rest[1..].join(" ");
+                return split_commands(&source).iter().flat_map(|c| effective_commands(c)).collect();
+            }
+            _ => return vec![rest.to_vec()],
+        }
+    }
+}
+
+/// The programs `command` runs, in order, looking through assignments and
+/// wrappers such as `sudo` or `env`.
+pub fn programs(command: &str) -> Vec<String> {
+    split_commands(command)
+        .iter()
+        .flat_map(|words| effective_commands(words))
+        .filter_map(|words| words.first().map(|program| basename(program).to_string()))
+        .collect()
+}
+
+/// Short flags of `args` (`-rf` yields `r` and `f`) plus the long ones.
+fn has_flag(args: &[String], short: char, long: &str) -> bool {
+    args.iter().any(|arg| {
+        if let Some(name) = arg.strip_prefix("--") {
+            name == long
+        } else if let Some(flags) = arg.strip_prefix('-') {
+            flags.chars().all(|c| c.is_ascii_alphanumeric()) && flags.contains(short)
+        } else {
+            false
+        }
+    })
+}
+
+fn builtin_findings(program: &str, args: &[String], findings: &mut Vec<String>) {
+    match program {
+        "rm" => {
+            let recursive = has_flag(args, 'r', "recursive") || has_flag(args, 'R', "recursive");
+            if recursive && has_flag(args, 'f', "force") {
+                findings.push("rm -rf".to_string());
+            }
+        }
+        "dd" if args.iter().any(|arg| arg.starts_with("of=/dev/")) => {
+            findings.push("dd onto a device".to_string());
+        }
+        p if p == "mkfs" || p.starts_with("mkfs.") => findings.push(p.to_string()),
+        "chmod" | "chown" => {
+            let recursive = has_flag(args, 'R', "recursive");
+            if recursive && args.iter().any(|arg| arg == "/" || arg == "/*") {
+                findings.push(format!("{} -R on /", program));
+            }
+        }
+        "git" => {
+            // Skip global options like `-C <dir>` and `-c key=value` to get
+            // to the subcommand.
+            let mut rest = args;
+            while let Some(option) = rest.first().filter(|arg| arg.starts_with('-')) {
+                let takes_value = git_option_takes_value(option);
+                rest = &rest[if takes_value { 2 } else { 1 }.min(rest.len())..];
+            }
+            if let Some((subcommand, push_args)) = rest.split_first()
+                && subcommand == "push"
+            {
+                let forced = has_flag(push_args, 'f', "force") || push_args.iter().any(|arg| arg.starts_with('+'));
+                if forced {
+                    findings.push("git push --force".to_string());
+                }
+            }
+        }
+        _ => {}
+    }
+
+    for arg in args {
+        let sql = arg.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        for statement in ["drop table", "drop database", "truncate table"] {
+            if sql.contains(statement) {
+                findings.push(statement.to_uppercase());
+            }
+        }
+    }
+}
+
+/// Detects `name(){ name|name& };name`-style fork bombs, however they are
+/// spaced.
+fn is_fork_bomb(source: &str) -> bool {
+    let compact: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut search_from = 0;
+    while let Some(found) = compact[search_from..].find("(){") {
+        let at = search_from + found;
+        let name_start = compact[..at]
+            .char_indices()
+            .rfind(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '.'))
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let name = &compact[name_start..at];
+        if !name.is_empty() {
+            let body = &compact[at + 3..];
+            let body = &body[..body.find('}').unwrap_or(body.len())];
+            if body.contains(&format!("{}|{}", name, name)) {
+                return true;
+            }
+        }
+        search_from = at + 3;
+    }
+    false
+}
+
+/// A user-defined rule from the rules file.
+///
+/// ```toml
+/// [[rule]]
+/// name = "kubectl delete namespace"
+/// program = "kubectl"
+/// args = ["delete", "namespace|ns"]
+/// ```
+///
+/// `program` must equal the command's program, every entry in `args` must
+/// match one of its arguments (`|` separates alternatives, a trailing `*`
+/// matches a prefix) and `contains` must occur in one of its arguments,
+/// ignoring case. Leaving a field out skips that check.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub program: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub contains: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+fn pattern_matches(pattern: &str, arg: &str) -> bool {
+    pattern.split('|').any(|alternative| match alternative.strip_suffix('*') {
+        Some(prefix) => arg.starts_with(prefix),
+        None => arg == alternative,
+    })
+}
+
+impl Rule {
+    fn matches(&self, program: &str, args: &[String]) -> bool {
+        if self.program.as_deref().is_some_and(|p| p != program) {
+            return false;
+        }
+        if !self.args.iter().all(|pattern| args.iter().any(|arg| pattern_matches(pattern, arg))) {
+            return false;
+        }
+        match &self.contains {
+            Some(needle) => {
+                let needle = needle.to_lowercase();
+                args.iter().any(|arg| arg.to_lowercase().contains(&needle))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Reads extra rules from `path`. A missing file means no extra rules; one
+/// that can't be parsed is an error, rather than quietly checking with
+/// fewer rules than the user wrote.
+pub fn load_rules(path: &str) -> Result<Vec<Rule>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io("read", path, e)),
+    };
+    let file: RulesFile = toml::from_str(&contents).map_err(|e| {
+        let line = e.span().map(|span| contents[..span.start].matches('\n').count() + 1);
+        Error::parse(path, line, e.message())
+    })?;
+    Ok(file.rules)
+}
+
+/// Why `command` looks destructive; empty if it doesn't.
+pub fn check(command: &str, rules: &[Rule]) -> Vec<String> {
+    let mut findings = Vec::new();
+    if is_fork_bomb(command) {
+        findings.push("fork bomb".to_string());
+    }
+
+    for words in split_commands(command) {
+        for words in effective_commands(&words) {
+            let Some((program, args)) = words.split_first() else {
+                continue;
+            };
+            let program = basename(program);
+            builtin_findings(program, args, &mut findings);
+            for rule in rules {
+                if rule.matches(program, args) {
+                    findings.push(rule.name.clone());
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    findings.retain(|finding| seen.insert(finding.clone()));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(commands: &[&[&str]]) -> Vec<Words> {
+        commands.iter().map(|command| command.iter().map(|word| word.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn splits_on_operators_and_resolves_quotes() {
+        assert_eq!(
+            split_commands(r#"cd /tmp && echo 'a b' "c\"d" e\ f | wc -l; ls &"#),
+            words(&[&["cd", "/tmp"], &["echo", "a b", "c\"d", "e f"], &["wc", "-l"], &["ls"]]),
+        );
+        assert_eq!(split_commands("echo '' x"), words(&[&["echo", "", "x"]]));
+        assert_eq!(split_commands("(cd src\nmake)"), words(&[&["cd", "src"], &["make"]]));
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(split_commands("ls # rm -rf /\npwd"), words(&[&["ls"], &["pwd"]]));
+        assert_eq!(split_commands("echo a#b"), words(&[&["echo", "a#b"]]));
+    }
+
+    #[test]
+    fn splits_substitutions_as_commands() {
+        assert_eq!(
+            split_commands("echo $(rm -rf x) `id -u`"),
+            words(&[&["rm", "-rf", "x"], &["id", "-u"], &["echo", "$(rm -rf x)", "`id -u`"]]),
+        );
+        assert_eq!(split_commands(r#"echo "$(date)""#), words(&[&["date"], &["echo", "$(date)"]]));
+    }
+
+    #[test]
+    fn skips_heredoc_bodies() {
+        let source = "cat <<EOF > out\nrm -rf /\nEOF\nls\ncat <<-'END'\n\trm -rf /\n\tEND\npwd";
+        assert_eq!(split_commands(source), words(&[&["cat", "out"], &["ls"], &["cat"], &["pwd"]]));
+    }
+
+    #[test]
+    fn here_strings_are_not_heredocs() {
+        assert_eq!(split_commands("cat <<< foo\nrm -rf /"), words(&[&["cat", "foo"], &["rm", "-rf", "/"]]));
+        assert_eq!(check("cat <<< foo\nrm -rf /", &[]), ["rm -rf"]);
+        assert_eq!(check("cat <<<EOF\nrm -rf /\nEOF", &[]), ["rm -rf"]);
+    }
+
+    #[test]
+    fn finds_builtin_patterns() {
+        assert_eq!(check("rm -rf build", &[]), ["rm -rf"]);
+        assert_eq!(check("rm -r --force build", &[]), ["rm -rf"]);
+        assert_eq!(check("dd if=image.iso of=/dev/sdb bs=4M", &[]), ["dd onto a device"]);
+        assert_eq!(check("mkfs.ext4 /dev/sdb1", &[]), ["mkfs.ext4"]);
+        assert_eq!(check("chmod -R 777 /", &[]), ["chmod -R on /"]);
+        assert_eq!(check("git push --force origin main", &[]), ["git push --force"]);
+        assert_eq!(check("git push origin +main", &[]), ["git push --force"]);
+        assert_eq!(check("git -C repo push -f", &[]), ["git push --force"]);
+        assert_eq!(check("git -c push.default=current --no-pager push --force", &[]), ["git push --force"]);
+        assert_eq!(check("psql -c 'DROP  TABLE users'", &[]), ["DROP TABLE"]);
+    }
+
+    #[test]
+    fn ignores_harmless_commands() {
+        for command in ["rm -r build", "rm file", "chmod -R 755 ./dist", "git push origin main", "dd if=a of=b", "git -C push status -f"] {
+            assert!(check(command, &[]).is_empty(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn ignores_quoted_text_and_comments() {
+        for command in ["echo 'rm -rf /'", "grep \"rm -rf\" notes.txt", "ls # rm -rf /", "cat <<EOF\nrm -rf /\nEOF"] {
+            assert!(check(command, &[]).is_empty(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn looks_through_wrappers() {
+        for command in [
+            "sudo -u root rm -rf /var/cache",
+            "FOO=1 env BAR=2 rm -rf x",
+            "find . -name '*.o' | xargs -n 10 rm -rf",
+            "sh -c 'cd / && rm -rf tmp'",
+            "bash -lc 'rm -rf x'",
+            "bash -e -o pipefail -c 'rm -rf x'",
+            "zsh --norc -ic 'rm -rf x'",
+            "echo $(rm -rf x)",
+            "timeout 10 rm -rf x",
+            "eval rm -rf x",
+        ] {
+            assert_eq!(check(command, &[]), ["rm -rf"], "{}", command);
+        }
+        // Without -c the arguments go to a script, not the shell.
+        assert!(check("bash script.sh -c 'rm -rf x'", &[]).is_empty());
+    }
+
+    #[test]
+    fn applies_custom_rules() {
+        let rules = vec![
+            Rule {
+                name: "kubectl delete namespace".to_string(),
+                program: Some("kubectl".to_string()),
+                args: vec!["delete".to_string(), "namespace|ns".to_string()],
+                contains: None,
+            },
+            Rule { name: "redis FLUSHALL".to_string(), program: None, args: Vec::new(), contains: Some("flushall".to_string()) },
+        ];
+        assert_eq!(check("kubectl delete ns staging", &rules), ["kubectl delete namespace"]);
+        assert!(check("kubectl delete pod web", &rules).is_empty());
+        assert_eq!(check("redis-cli FLUSHALL", &rules), ["redis FLUSHALL"]);
+    }
+
+    #[test]
+    fn loads_rules_and_reports_where_a_file_is_broken() {
+        let dir = std::env::temp_dir().join(format!("oneliners-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rules.toml").to_str().unwrap().to_string();
+
+        assert!(load_rules(&path).unwrap().is_empty());
+        fs::write(&path, "[[rule]]\nname = \"redis FLUSHALL\"\ncontains = \"flushall\"\n").unwrap();
+        assert_eq!(check("redis-cli FLUSHALL", &load_rules(&path).unwrap()), ["redis FLUSHALL"]);
+        fs::write(&path, "[[rule]]\nname = \"redis FLUSHALL\"\ncontains = flushall\n").unwrap();
+        let error = load_rules(&path).unwrap_err();
+        assert!(matches!(&error, Error::Parse { line: Some(3), .. }), "{}", error);
+        assert_eq!(error.exit_code(), crate::error::EXIT_PARSE);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn detects_fork_bombs() {
+        assert!(is_fork_bomb(":(){ :|:& };:"));
+        assert!(is_fork_bomb(": ( ) { : | : & } ; :"));
+        assert!(is_fork_bomb("bomb(){ bomb|bomb& }; bomb"));
+        assert_eq!(check(":(){ :|:& };:", &[]), ["fork bomb"]);
+        assert!(!is_fork_bomb("f(){ echo hi | wc; }; f"));
+        assert!(!is_fork_bomb("a(){ b|b& }"));
+    }
+
+    #[test]
+    fn fork_bomb_check_handles_multibyte_text() {
+        assert!(!is_fork_bomb("echo \u{201c}a(){ b; }"));
+        assert!(is_fork_bomb("echo \u{201c}; \u{e9}t\u{e9}(){ \u{e9}t\u{e9}|\u{e9}t\u{e9}& }"));
+        assert!(check("echo \u{201c}a(){ b; }", &[]).is_empty());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::snippet::Snippet;
+use std::fmt;
+use std::io::IsTerminal;
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY_WHITE: i64 = 10;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// Added once per term that also occurs verbatim, so exact hits always
+/// outrank scattered ones of similar length.
+const BONUS_EXACT: i64 = 32;
+/// Hits in a description count for this share of a hit in the command.
+const DESCRIPTION_WEIGHT: (i64, i64) = (2, 3);
+
+const NONE: i64 = i64::MIN / 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Char (not byte) offsets of the matched characters, ascending.
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Subsequence matching with fzf-style scoring.
+    #[default]
+    Fuzzy,
+    /// Plain case-insensitive substring matching.
+    Exact,
+}
+
+fn bonus_at(chars: &[char], j: usize) -> i64 {
+    if j == 0 {
+        return BONUS_BOUNDARY_WHITE;
+    }
+    let prev = chars[j - 1];
+    let cur = chars[j];
+    if prev.is_whitespace() {
+        BONUS_BOUNDARY_WHITE
+    } else if "/-_.:|;=,'\"()[]{}$@".contains(prev) && cur.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && cur.is_uppercase() {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) }
+}
+
+/// Best-scoring alignment of `pattern` as a subsequence of `text`.
+fn fuzzy_term(pattern: &[char], text: &[char], case_sensitive: bool) -> Option<Match> {
+    let (m, n) = (pattern.len(), text.len());
+    if m == 0 {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+    if m > n {
+        return None;
+    }
+
+    let folded: Vec<char> = text.iter().map(|&c| fold(c, case_sensitive)).collect();
+    let bonus: Vec<i64> = (0..n).map(|j| bonus_at(text, j)).collect();
+
+    // score[i][j]: best score with pattern[i] matched at text[j].
+    // from[i][j]: where pattern[i - 1] was matched on that best path.
+    let mut score = vec![vec![NONE; n]; m];
+    let mut from = vec![vec![usize::MAX; n]; m];
+
+    for i in 0..m {
+        let pc = fold(pattern[i], case_sensitive);
+        let mut gap_best = NONE;
+        let mut gap_from = usize::MAX;
+        for j in 0..n {
+            let consecutive = if i > 0 && j > 0 { score[i - 1][j - 1] } else { NONE };
+
+            if folded[j] == pc {
+                if i == 0 {
+                    score[i][j] = SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER;
+                } else {
+                    let via_consecutive = if consecutive > NONE {
+                        consecutive + BONUS_CONSECUTIVE
+                    } else {
+                        NONE
+                    };
+                    if via_consecutive > NONE || gap_best > NONE {
+                        if via_consecutive >= gap_best {
+                            score[i][j] = via_consecutive + SCORE_MATCH + bonus[j];
+                            from[i][j] = j - 1;
+                        } else {
+                            score[i][j] = gap_best + SCORE_MATCH + bonus[j];
+                            from[i][j] = gap_from;
+                        }
+                    }
+                }
+            }
+
+            if i > 0 {
+                // Skipping text[j] either opens a gap after a match at j - 1
+                // or extends one that is already open.
+                let extended = if gap_best > NONE { gap_best + SCORE_GAP_EXTENSION } else { NONE };
+                let opened = if consecutive > NONE { consecutive + SCORE_GAP_START } else { NONE };
+                if opened >= extended && opened > NONE {
+                    gap_best = opened;
+                    gap_from = j - 1;
+                } else {
+                    gap_best = extended;
+                }
+            }
+        }
+    }
+
+    let (end, &best) = score[m - 1]
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| **s > NONE)
+        .max_by_key(|(j, s)| (**s, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![0; m];
+    let mut j = end;
+    for i in (0..m).rev() {
+        positions[i] = j;
+        if i > 0 {
+            j = from[i][j];
+        }
+    }
+
+    Some(Match { score: best, positions })
+}
+
+fn exact_term(pattern: &[char], text: &[char], case_sensitive: bool) -> Option<Match> {
+    if pattern.is_empty() {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+    let folded: Vec<char> = text.iter().map(|&c| fold(c, case_sensitive)).collect();
+    let needle: Vec<char> = pattern.iter().map(|&c| fold(c, case_sensitive)).collect();
+
+    (0..=folded.len().saturating_sub(needle.len()))
+        .filter(|&start| folded[start..].starts_with(&needle))
+        .map(|start| {
+            let score = SCORE_MATCH * needle.len() as i64
+                + bonus_at(text, start) * BONUS_FIRST_CHAR_MULTIPLIER
+                + BONUS_CONSECUTIVE * (needle.len() as i64 - 1)
+                + BONUS_EXACT;
+            Match { score, positions: (start..start + needle.len()).collect() }
+        })
+        .max_by_key(|m| (m.score, std::cmp::Reverse(m.positions[0])))
+}
+
+/// A search as typed: `tag:<name>` terms select snippets carrying that tag,
+/// the remaining terms are matched against the text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub tags: Vec<String>,
+    pub text: String,
+    pub mode: Mode,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Query {
+        let mut parsed = Query::default();
+        let mut text = Vec::new();
+        for term in query.split_whitespace() {
+            match term.strip_prefix("tag:") {
+                Some(tag) if !tag.is_empty() => parsed.tags.push(tag.to_lowercase()),
+                _ => text.push(term),
+            }
+        }
+        parsed.text = text.join(" ");
+        parsed
+    }
+
+    pub fn with_mode(mut self, mode: Mode) -> Query {
+        self.mode = mode;
+        self
+    }
+
+    /// Whether `tags` includes every tag the query asks for.
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        self.tags.iter().all(|wanted| tags.contains(wanted))
+    }
+}
+
+/// The query as it could have been typed, with the tag terms first.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<String> =
+            self.tags.iter().map(|tag| format!("tag:{}", tag)).chain(self.text.split_whitespace().map(str::to_string)).collect();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+fn match_term(term: &str, text: &[char], mode: Mode, case_sensitive: bool) -> Option<Match> {
+    let (term, exact) = match term.strip_prefix('\'') {
+        Some(rest) => (rest, true),
+        None => (term, mode == Mode::Exact),
+    };
+    let pattern: Vec<char> = term.chars().collect();
+
+    let exact_hit = exact_term(&pattern, text, case_sensitive);
+    if exact {
+        return exact_hit;
+    }
+    match (fuzzy_term(&pattern, text, case_sensitive), exact_hit) {
+        (Some(fuzzy), Some(exact)) if exact.score >= fuzzy.score => Some(exact),
+        (fuzzy, _) => fuzzy,
+    }
+}
+
+/// Scores `text` against a whitespace-separated query. Every term has to
+/// match; a term prefixed with `'` must occur verbatim. Matching is
+/// case-insensitive unless the query contains an uppercase letter.
+pub fn match_text(query: &str, text: &str, mode: Mode) -> Option<Match> {
+    match_described(query, text, None, mode)
+}
+
+/// Like `match_text`, but each term may also match `description`, for a
+/// lower score. Only positions in `text` are reported.
+pub fn match_described(query: &str, text: &str, description: Option<&str>, mode: Mode) -> Option<Match> {
+    let case_sensitive = query.chars().any(|c| c.is_uppercase());
+    let text: Vec<char> = text.chars().collect();
+    let description: Vec<char> = description.unwrap_or_default().chars().collect();
+
+    let mut total = Match { score: 0, positions: Vec::new() };
+    for term in query.split_whitespace() {
+        let in_text = match_term(term, &text, mode, case_sensitive);
+        let in_description = match_term(term, &description, mode, case_sensitive)
+            .filter(|_| !description.is_empty())
+            .map(|m| m.score * DESCRIPTION_WEIGHT.0 / DESCRIPTION_WEIGHT.1);
+        match (in_text, in_description) {
+            (Some(hit), Some(score)) if score > hit.score => total.score += score,
+            (Some(hit), _) => {
+                total.score += hit.score;
+                total.positions.extend(hit.positions);
+            }
+            (None, Some(score)) => total.score += score,
+            (None, None) => return None,
+        }
+    }
+
+    total.positions.sort_unstable();
+    total.positions.dedup();
+    Some(total)
+}
+
+/// Whether `text` contains `pattern` as written, spaces and all. Like the
+/// other matching, case only matters when `pattern` has an uppercase letter.
+pub fn contains(pattern: &str, text: &str) -> bool {
+    if pattern.chars().any(|c| c.is_uppercase()) {
+        text.contains(pattern)
+    } else {
+        text.to_lowercase().contains(&pattern.to_lowercase())
+    }
+}
+
+/// Ranks every snippet against the query, best first. Ties go to the
+/// shorter command, then to the older snippet.
+pub fn rank<'a>(query: &Query, snippets: &'a [Snippet]) -> Vec<(&'a Snippet, Match)> {
+    let mut ranked: Vec<(&Snippet, Match)> = snippets
+        .iter()
+        .filter(|snippet| query.matches_tags(&snippet.tags))
+        .filter_map(|snippet| {
+            match_described(&query.text, &snippet.command, snippet.description.as_deref(), query.mode)
+                .map(|m| (snippet, m))
+        })
+        .collect();
+
+    ranked.sort_by(|(a, am), (b, bm)| {
+        bm.score
+            .cmp(&am.score)
+            .then(a.command.len().cmp(&b.command.len()))
+            .then(a.id.cmp(&b.id))
+    });
+    ranked
+}
+
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Wraps the matched characters of `text` in bold yellow.
+pub fn highlight(text: &str, positions: &[usize]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut positions = positions.iter().peekable();
+    let mut open = false;
+    for (i, c) in text.chars().enumerate() {
+        let hit = positions.peek() == Some(&&i);
+        if hit {
+            positions.next();
+        }
+        if hit && !open {
+            out.push_str("\x1b[1;33m");
+            open = true;
+        } else if !hit && open {
+            out.push_str("\x1b[0m");
+            open = false;
+        }
+        out.push(c);
+    }
+    if open {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, text: &str) -> Option<Vec<usize>> {
+        match_text(query, text, Mode::Fuzzy).map(|m| m.positions)
+    }
+
+    fn ranked(query: &str, snippets: &[Snippet]) -> Vec<u64> {
+        rank(&Query::parse(query), snippets).into_iter().map(|(s, _)| s.id).collect()
+    }
+
+    fn snippets(commands: &[&str]) -> Vec<Snippet> {
+        commands.iter().enumerate().map(|(i, command)| Snippet::new(i as u64 + 1, command)).collect()
+    }
+
+    #[test]
+    fn every_term_has_to_match_in_order_within_the_term() {
+        assert!(match_text("gco", "git checkout", Mode::Fuzzy).is_some());
+        assert!(match_text("git log", "git checkout", Mode::Fuzzy).is_none());
+        assert!(match_text("ocg", "git checkout", Mode::Fuzzy).is_none());
+        // Terms are matched independently, so their order doesn't matter.
+        assert!(match_text("checkout git", "git checkout", Mode::Fuzzy).is_some());
+        assert!(match_text("", "anything", Mode::Fuzzy).is_some());
+    }
+
+    #[test]
+    fn positions_prefer_word_starts_and_runs() {
+        assert_eq!(positions("gc", "git checkout"), Some(vec![0, 4]));
+        assert_eq!(positions("check", "git checkout"), Some(vec![4, 5, 6, 7, 8]));
+        assert_eq!(positions("ps", "docker ps -a"), Some(vec![7, 8]));
+        // Overlapping terms report each position once.
+        assert_eq!(positions("git it", "git"), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(match_text("grep", "GREP -R foo", Mode::Fuzzy).is_some());
+        assert!(match_text("Grep", "grep -r foo", Mode::Fuzzy).is_none());
+        assert!(match_text("R", "grep -r foo", Mode::Exact).is_none());
+        assert!(match_text("R", "grep -R foo", Mode::Exact).is_some());
+    }
+
+    #[test]
+    fn contains_matches_the_whole_pattern() {
+        assert!(contains("rm -rf", "sudo RM -RF /tmp/x"));
+        assert!(!contains("rm -rf", "rm -r -f /tmp/x"));
+        assert!(!contains("-rf rm", "rm -rf /tmp/x"));
+        assert!(contains("'x'", "echo 'x'"));
+        assert!(!contains("RM", "rm -rf"));
+        assert!(contains("café", "echo CAFÉ"));
+    }
+
+    #[test]
+    fn quoted_terms_must_occur_verbatim() {
+        assert!(match_text("gco", "git checkout", Mode::Fuzzy).is_some());
+        assert!(match_text("'gco", "git checkout", Mode::Fuzzy).is_none());
+        assert_eq!(match_text("'check", "git checkout", Mode::Fuzzy).map(|m| m.positions), Some(vec![4, 5, 6, 7, 8]));
+        assert!(match_text("gco", "git checkout", Mode::Exact).is_none());
+        assert!(match_text("'", "git checkout", Mode::Fuzzy).is_some());
+    }
+
+    #[test]
+    fn multibyte_text_reports_char_positions() {
+        assert_eq!(positions("café", "echo café crème"), Some(vec![5, 6, 7, 8]));
+        assert_eq!(positions("cr", "echo café crème"), Some(vec![10, 11]));
+        assert_eq!(positions("ÉCHO", "ÉCHO ÜBER"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(match_text("über", "échö ÜBER", Mode::Exact).map(|m| m.positions), Some(vec![5, 6, 7, 8]));
+        assert_eq!(highlight("né", &[1]), "n\x1b[1;33mé\x1b[0m");
+    }
+
+    #[test]
+    fn exact_and_boundary_hits_rank_first() {
+        let found = snippets(&["gzip -c archive", "git commit -a", "grep -c pattern"]);
+        assert_eq!(ranked("commit", &found), [2]);
+        // A verbatim hit beats a scattered one.
+        let found = snippets(&["tar cxzf out.tar.gz src", "docker ps -a", "ps aux"]);
+        assert_eq!(ranked("ps", &found), [3, 2]);
+    }
+
+    #[test]
+    fn ties_go_to_the_shorter_then_older_snippet() {
+        let found = snippets(&["ls -la /var/log", "ls -la", "ls -lh", "ls -la"]);
+        assert_eq!(ranked("ls", &found), [2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn description_hits_count_for_less() {
+        let mut found = snippets(&["journalctl -f", "tail -f /var/log/syslog"]);
+        found[0].description = Some("follow the system log".to_string());
+        assert_eq!(ranked("log", &found), [2, 1]);
+        let m = match_described("follow", "journalctl -f", Some("follow the system log"), Mode::Fuzzy).unwrap();
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    fn tag_terms_filter_before_matching() {
+        let mut found = snippets(&["kubectl get pods", "docker ps"]);
+        found[0].tags = vec!["k8s".to_string()];
+        let query = Query::parse("tag:K8S get");
+        assert_eq!(query.tags, ["k8s"]);
+        assert_eq!(query.text, "get");
+        assert_eq!(query.to_string(), "tag:k8s get");
+        assert_eq!(ranked("tag:k8s", &found), [1]);
+        assert!(ranked("tag:k8s docker", &found).is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single stored snippet. Timestamps are seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: u64,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub created: u64,
+    pub updated: u64,
+    /// Set at store time when the command matches a destructive pattern.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dangerous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_status: Option<i32>,
+    /// When the snippet was last copied, printed or run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub use_count: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl Snippet {
+    pub fn new(id: u64, command: &str) -> Snippet {
+        let now = now();
+        Snippet {
+            id,
+            command: command.to_string(),
+            description: None,
+            tags: Vec::new(),
+            created: now,
+            updated: now,
+            dangerous: false,
+            last_run: None,
+            last_status: None,
+            last_used: None,
+            use_count: 0,
+        }
+    }
+}
+
+/// The parts of a snippet a user writes, before it gets an id and
+/// timestamps.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Draft {
+    pub command: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Draft {
+    pub fn from_snippet(snippet: &Snippet) -> Draft {
+        Draft {
+            command: snippet.command.clone(),
+            description: snippet.description.clone(),
+            tags: snippet.tags.clone(),
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats a Unix timestamp as local `YYYY-MM-DD HH:MM`.
+pub fn format_time(secs: u64) -> String {
+    let time = secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return secs.to_string();
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+/// Unifies line endings and trims surrounding whitespace, keeping the
+/// inner lines of multi-line snippets as they are.
+pub fn normalize_command(command: &str) -> String {
+    command.replace("\r\n", "\n").trim().to_string()
+}
+
+/// Indents the continuation lines of `text` by `width` columns, so a
+/// multi-line snippet lines up under a prefix of that width.
+pub fn indent_continuation(text: &str, width: usize) -> String {
+    text.replace('\n', &format!("\n{}", " ".repeat(width)))
+}
+
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || "-_.:/".contains(c))
+}
+
+/// Tags worth suggesting for `command`: the name of the program it runs,
+/// looking past variable assignments and `sudo`.
+pub fn suggest_tags(command: &str) -> Vec<String> {
+    let program = crate::safety::programs(command)
+        .into_iter()
+        .find(|program| !matches!(program.as_str(), "for" | "case" | "select" | "function"))
+        .unwrap_or_default()
+        .to_lowercase();
+    // Scripts (`deploy.sh`) make poor tags.
+    if is_valid_tag(&program) && !program.contains('.') { vec![program] } else { Vec::new() }
+}
+
+/// Lowercases tags and drops empty ones and duplicates, keeping the order.
+pub fn normalize_tags<S: AsRef<str>>(tags: &[S]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.as_ref().trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Validates tags as given on the command line, where one value may also
+/// hold several comma-separated tags, and normalizes them.
+pub fn parse_tags<S: AsRef<str>>(given: &[S]) -> Result<Vec<String>> {
+    let tags: Vec<&str> = given.iter().flat_map(|tag| tag.as_ref().split(',')).map(str::trim).collect();
+    if let Some(invalid) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+        return Err(Error::Invalid(format!("invalid tag '{}'; use letters, digits and - _ . : /", invalid)));
+    }
+    Ok(normalize_tags(&tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_the_program_as_a_tag() {
+        assert_eq!(suggest_tags("kubectl get pods"), ["kubectl"]);
+        assert_eq!(suggest_tags("RUST_LOG=debug CARGO_TERM_COLOR=never cargo test"), ["cargo"]);
+        assert_eq!(suggest_tags("sudo systemctl restart nginx"), ["systemctl"]);
+        assert_eq!(suggest_tags("sudo -u postgres psql -c 'select 1'"), ["psql"]);
+        assert_eq!(suggest_tags("LANG=C sudo Docker ps"), ["docker"]);
+        assert_eq!(suggest_tags("/usr/bin/git log --oneline | head"), ["git"]);
+        assert_eq!(suggest_tags("for f in *.log; do gzip \"$f\"; done"), ["gzip"]);
+    }
+
+    #[test]
+    fn suggests_nothing_for_scripts_or_empty_commands() {
+        assert!(suggest_tags("./deploy.sh production").is_empty());
+        assert!(suggest_tags("FOO=1").is_empty());
+        assert!(suggest_tags("").is_empty());
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tags(&[" Docker", "k8s", "docker ", "", "K8S", "net"]), ["docker", "k8s", "net"]);
+        assert_eq!(parse_tags(&["Git,ci", " net , git", "aws:prod", "a/b"]).unwrap(), ["git", "ci", "net", "aws:prod", "a/b"]);
+        assert!(parse_tags::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_tags() {
+        for given in ["two words", "a,,b", "semi;colon", "trailing,"] {
+            let error = parse_tags(&[given]).unwrap_err();
+            assert!(error.to_string().starts_with("invalid tag '"), "{}", error);
+        }
+        assert_eq!(parse_tags(&["ok", "b@d"]).unwrap_err().to_string(), "invalid tag 'b@d'; use letters, digits and - _ . : /");
+    }
+}
@@ -0,0 +1,704 @@
+use crate::error::{Context, Error, Result};
+use crate::oplog::{self, Operation, Version};
+use crate::safety::{self, Rule};
+use crate::search::{self, Match, Mode, Query};
+use crate::snippet::{self, Draft, Snippet};
+use std::cmp::Reverse;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+/// Version of the record format written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// First line of a record-format store, followed by the format version.
+const HEADER_PREFIX: &str = "#oneliners v";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Missing or empty file.
+    Empty,
+    /// Plain text, one command per line, as written by older releases.
+    Legacy,
+    /// Header line followed by one JSON record per line.
+    Records(u32),
+}
+
+fn header() -> String {
+    format!("{}{}", HEADER_PREFIX, FORMAT_VERSION)
+}
+
+/// The lines of the store without line endings. A line that isn't valid
+/// UTF-8 is an error rather than being skipped, so it can't be lost when
+/// the store is saved again.
+pub fn read_lines(file_path: &str) -> Result<Vec<String>> {
+    let mut bytes = match fs::read(file_path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::io("read", file_path, e)),
+    };
+    if bytes.last() == Some(&b'\n') {
+        bytes.pop();
+    }
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    bytes
+        .split(|&b| b == b'\n')
+        .enumerate()
+        .map(|(i, line)| match std::str::from_utf8(line) {
+            Ok(line) => Ok(line.trim_end_matches('\r').to_string()),
+            Err(_) => Err(Error::parse(file_path, Some(i + 1), "invalid UTF-8")),
+        })
+        .collect()
+}
+
+pub fn format_of(lines: &[String]) -> Format {
+    match lines.iter().find(|line| !line.trim().is_empty()) {
+        None => Format::Empty,
+        Some(first) => match first.trim().strip_prefix(HEADER_PREFIX) {
+            Some(version) => version.parse().map(Format::Records).unwrap_or(Format::Legacy),
+            None => Format::Legacy,
+        },
+    }
+}
+
+pub fn detect_format(file_path: &str) -> Result<Format> {
+    Ok(format_of(&read_lines(file_path)?))
+}
+
+pub fn modified_secs(file_path: &str) -> u64 {
+    fs::metadata(file_path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn parse_legacy(lines: &[String], file_path: &str) -> Vec<Snippet> {
+    let stamp = modified_secs(file_path);
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let mut snippet = Snippet::new(i as u64 + 1, line);
+            snippet.created = stamp;
+            snippet.updated = stamp;
+            snippet
+        })
+        .collect()
+}
+
+fn parse_records(lines: &[String], version: u32, file_path: &str) -> Result<Vec<Snippet>> {
+    if version > FORMAT_VERSION {
+        return Err(Error::parse(
+            file_path,
+            None,
+            format!("store uses format v{}, this build only understands up to v{}", version, FORMAT_VERSION),
+        ));
+    }
+
+    let mut snippets = Vec::new();
+    // The header is always the first non-blank line, so skip past it.
+    let body = lines.iter().enumerate().skip_while(|(_, line)| line.trim().is_empty()).skip(1);
+    for (i, line) in body {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snippet: Snippet =
+            serde_json::from_str(line).map_err(|e| Error::parse(file_path, Some(i + 1), e.to_string()))?;
+        snippets.push(snippet);
+    }
+    Ok(snippets)
+}
+
+/// Reads every snippet from the store, accepting both the record format and
+/// legacy plain-line files. A missing file is an empty store.
+pub fn load(file_path: &str) -> Result<Vec<Snippet>> {
+    let lines = read_lines(file_path)?;
+    match format_of(&lines) {
+        Format::Empty => Ok(Vec::new()),
+        Format::Legacy => Ok(parse_legacy(&lines, file_path)),
+        Format::Records(version) => parse_records(&lines, version, file_path),
+    }
+}
+
+/// Creates the directory `path` lives in, which doesn't exist yet on first
+/// use of an XDG location.
+fn create_parent(path: &str) -> io::Result<()> {
+    match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces `path` with `contents` without ever leaving a partly written
+/// file behind: the data goes to a temporary file in the same directory,
+/// is synced to disk and then renamed over `path`.
+pub fn write_atomic(path: &str, contents: &[u8]) -> io::Result<()> {
+    let temp_path = format!("{}.tmp-{}", path, std::process::id());
+    let result = (|| {
+        create_parent(path)?;
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        // Sync the directory too, so the rename itself survives a crash.
+        let dir = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        File::open(dir)?.sync_all()
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// An exclusive advisory lock on the store, released when dropped. It is
+/// taken on a separate `.lock` file because saving replaces the store file
+/// itself.
+pub struct Lock {
+    _file: File,
+}
+
+pub fn lock(file_path: &str) -> Result<Lock> {
+    let lock_path = format!("{}.lock", file_path);
+    create_parent(&lock_path).context("lock", &lock_path)?;
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path).context("lock", &lock_path)?;
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+            return Ok(Lock { _file: file });
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(Error::io("lock", lock_path, error));
+        }
+    }
+}
+
+/// Rewrites the whole store in the current record format. Callers that
+/// read the store first should hold the lock throughout; see `update`.
+pub fn save(file_path: &str, snippets: &[Snippet]) -> Result<()> {
+    let mut out = header();
+    out.push('\n');
+    for snippet in snippets {
+        out.push_str(&serde_json::to_string(snippet).context("write", file_path)?);
+        out.push('\n');
+    }
+    write_atomic(file_path, out.as_bytes()).context("write", file_path)
+}
+
+/// Loads the store, lets `change` modify it and saves the result, all under
+/// the store lock so concurrent writers can't lose each other's changes.
+/// Nothing is written when `change` returns `None`. What changed is
+/// recorded in the operation log under the name `op`, so it can be undone.
+pub fn update<T>(
+    file_path: &str,
+    op: &str,
+    change: impl FnOnce(&mut Vec<Snippet>) -> Option<T>,
+) -> Result<Option<T>> {
+    let _lock = lock(file_path)?;
+    let seq = oplog::prepare(file_path)?;
+    let before = load(file_path)?;
+    let mut snippets = before.clone();
+    let result = change(&mut snippets);
+    if result.is_some() {
+        save(file_path, &snippets)?;
+        oplog::record(file_path, seq, op, Vec::new(), oplog::diff(&before, &snippets))?;
+    }
+    Ok(result)
+}
+
+/// Like `update`, but not logged. Meant for bookkeeping such as run
+/// statistics that shouldn't show up in `undo` or a snippet's history.
+pub fn update_stats(file_path: &str, change: impl FnOnce(&mut Vec<Snippet>) -> Option<()>) -> Result<()> {
+    let _lock = lock(file_path)?;
+    let mut snippets = load(file_path)?;
+    if change(&mut snippets).is_some() {
+        save(file_path, &snippets)?;
+    }
+    Ok(())
+}
+
+/// One more than the highest id in `snippets`.
+pub fn next_id(snippets: &[Snippet]) -> u64 {
+    snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1
+}
+
+fn command_exists(snippets: &[Snippet], command: &str) -> bool {
+    snippets.iter().any(|snippet| snippet.command.trim() == command.trim())
+}
+
+/// Orders `list` can show snippets in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Created,
+    LastUsed,
+    UseCount,
+    Alpha,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 4] = [SortKey::Created, SortKey::LastUsed, SortKey::UseCount, SortKey::Alpha];
+
+    /// What `--sort` calls it.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Created => "created",
+            SortKey::LastUsed => "last-used",
+            SortKey::UseCount => "use-count",
+            SortKey::Alpha => "alpha",
+        }
+    }
+
+    pub fn about(self) -> &'static str {
+        match self {
+            SortKey::Created => "Oldest first",
+            SortKey::LastUsed => "Most recently copied or run first",
+            SortKey::UseCount => "Most often copied or run first",
+            SortKey::Alpha => "By command, A to Z",
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<SortKey> {
+        SortKey::ALL.into_iter().find(|key| key.name() == name).ok_or_else(|| Error::Invalid(format!("unknown sort order '{}'", name)))
+    }
+}
+
+/// Which snippets `list` shows, and in what order.
+#[derive(Debug, Clone, Default)]
+pub struct Listing {
+    /// Only snippets carrying every one of these tags.
+    pub tags: Vec<String>,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Listing {
+    /// Narrows down and orders `snippets`.
+    pub fn apply(&self, mut snippets: Vec<Snippet>) -> Vec<Snippet> {
+        snippets.retain(|snippet| self.tags.iter().all(|tag| snippet.tags.contains(tag)));
+        match self.sort {
+            SortKey::Created => snippets.sort_by_key(|s| (s.created, s.id)),
+            SortKey::LastUsed => snippets.sort_by_key(|s| (Reverse(s.last_used), s.id)),
+            SortKey::UseCount => snippets.sort_by_key(|s| (Reverse(s.use_count), s.id)),
+            SortKey::Alpha => {
+                snippets.sort_by(|a, b| a.command.to_lowercase().cmp(&b.command.to_lowercase()).then(a.id.cmp(&b.id)))
+            }
+        }
+        if self.reverse {
+            snippets.reverse();
+        }
+        snippets.into_iter().skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect()
+    }
+}
+
+/// Somewhere snippets are kept. Implementors provide reading, a
+/// read-modify-write that is safe against concurrent writers and the
+/// history of those changes; everything else is built on top.
+pub trait SnippetStore {
+    fn snippets(&self) -> Result<Vec<Snippet>>;
+
+    /// Lets `change` modify the snippets and saves them when it returns
+    /// true, recording the change under the name `op`. Returns whether
+    /// anything was saved.
+    fn modify(&self, op: &str, change: &mut dyn FnMut(&mut Vec<Snippet>) -> bool) -> Result<bool>;
+
+    /// Counts a use of snippet `id`; `status` is the exit status when it
+    /// was run. Not recorded as a change.
+    fn record_use(&self, id: u64, status: Option<i32>) -> Result<()>;
+
+    /// Reverts the last `count` changes that haven't been undone yet, and
+    /// returns them, newest first.
+    fn undo(&self, count: usize) -> Result<Vec<Operation>>;
+
+    /// Every recorded state of snippet `id`, oldest first.
+    fn versions(&self, id: u64) -> Result<Vec<Version>>;
+
+    /// Makes `version` the current state of its snippet, bringing it back
+    /// if it was deleted.
+    fn restore(&self, version: &Snippet) -> Result<()>;
+
+    /// Custom rules for flagging destructive commands, on top of the
+    /// built-in ones.
+    fn rules(&self) -> &[Rule] {
+        &[]
+    }
+
+    /// The id for a new snippet, given the current `snippets`. Ids are never
+    /// handed out twice, so a store that keeps history has to look past
+    /// the snippets deleted since.
+    fn next_id(&self, snippets: &[Snippet]) -> Result<u64> {
+        Ok(next_id(snippets))
+    }
+
+    fn get(&self, id: u64) -> Result<Snippet> {
+        self.snippets()?.into_iter().find(|s| s.id == id).ok_or(Error::NotFound(id))
+    }
+
+    /// Every snippet matching `query`, best first.
+    fn search(&self, query: &Query) -> Result<Vec<(Snippet, Match)>> {
+        let snippets = self.snippets()?;
+        Ok(search::rank(query, &snippets).into_iter().map(|(s, m)| (s.clone(), m)).collect())
+    }
+
+    /// The best `limit` matches for `query`. Unlike `search`, finding
+    /// nothing is an error.
+    fn find(&self, query: &Query, limit: usize) -> Result<Vec<(Snippet, Match)>> {
+        let mut matches = self.search(query)?;
+        if matches.is_empty() {
+            return Err(if self.snippets()?.is_empty() { Error::EmptyStore } else { Error::NoMatches(query.to_string()) });
+        }
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /// The snippet `target` names: its id, or else a search that only one
+    /// snippet matches, or that one snippet's command equals.
+    fn resolve(&self, target: &str, mode: Mode) -> Result<Snippet> {
+        let snippets = self.snippets()?;
+        if let Ok(id) = target.parse::<u64>()
+            && let Some(snippet) = snippets.iter().find(|s| s.id == id)
+        {
+            return Ok(snippet.clone());
+        }
+        if snippets.is_empty() {
+            return Err(Error::EmptyStore);
+        }
+
+        let matches = search::rank(&Query::parse(target).with_mode(mode), &snippets);
+        let mut equal = matches.iter().filter(|(snippet, _)| snippet.command.trim() == target.trim());
+        match (matches.as_slice(), equal.next(), equal.next()) {
+            ([], _, _) => Err(Error::NoMatches(target.to_string())),
+            ([(snippet, _)], _, _) | (_, Some((snippet, _)), None) => Ok((*snippet).clone()),
+            _ => Err(Error::Ambiguous {
+                query: target.to_string(),
+                candidates: matches.iter().take(10).map(|(s, _)| (s.id, s.command.clone())).collect(),
+            }),
+        }
+    }
+
+    fn list(&self, listing: &Listing) -> Result<Vec<Snippet>> {
+        Ok(listing.apply(self.snippets()?))
+    }
+
+    /// The tags in use and how many snippets carry each, most used first.
+    fn tags(&self) -> Result<Vec<(String, usize)>> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for tag in self.snippets()?.iter().flat_map(|snippet| &snippet.tags) {
+            match counts.iter_mut().find(|(name, _)| name == tag) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((tag.clone(), 1)),
+            }
+        }
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        Ok(counts)
+    }
+
+    /// Why running `command`, filled in from `snippet`, looks destructive;
+    /// empty if it doesn't.
+    fn review(&self, snippet: &Snippet, command: &str) -> Vec<String> {
+        let mut findings = safety::check(command, self.rules());
+        if snippet.dangerous && findings.is_empty() {
+            findings.push("marked dangerous when stored".to_string());
+        }
+        findings
+    }
+
+    /// Stores a new snippet, unless its command is already stored.
+    fn add(&self, draft: &Draft) -> Result<Snippet> {
+        if draft.command.trim().is_empty() {
+            return Err(Error::Invalid("nothing to store".to_string()));
+        }
+        let dangerous = !safety::check(&draft.command, self.rules()).is_empty();
+        let mut outcome = Err(Error::Invalid("nothing to store".to_string()));
+        self.modify("store", &mut |snippets| {
+            if let Some(existing) = snippets.iter().find(|s| s.command.trim() == draft.command.trim()) {
+                outcome = Err(Error::Duplicate(existing.id));
+                return false;
+            }
+            let id = match self.next_id(snippets) {
+                Ok(id) => id,
+                Err(e) => {
+                    outcome = Err(e);
+                    return false;
+                }
+            };
+            let mut snippet = Snippet::new(id, &draft.command);
+            snippet.description = draft.description.clone();
+            snippet.tags = draft.tags.clone();
+            snippet.dangerous = dangerous;
+            snippets.push(snippet.clone());
+            outcome = Ok(snippet);
+            true
+        })?;
+        outcome
+    }
+
+    /// Stores every command that isn't stored yet, as one change, and
+    /// returns the new snippets.
+    fn import(&self, commands: &[String]) -> Result<Vec<Snippet>> {
+        let mut imported = Vec::new();
+        let mut failure = None;
+        self.modify("import", &mut |snippets| {
+            imported.clear();
+            let mut id = match self.next_id(snippets) {
+                Ok(id) => id,
+                Err(e) => {
+                    failure = Some(e);
+                    return false;
+                }
+            };
+            for command in commands {
+                if command.trim().is_empty() || command_exists(snippets, command) {
+                    continue;
+                }
+                let mut snippet = Snippet::new(id, command);
+                id += 1;
+                snippet.dangerous = !safety::check(command, self.rules()).is_empty();
+                snippets.push(snippet.clone());
+                imported.push(snippet);
+            }
+            !imported.is_empty()
+        })?;
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(imported),
+        }
+    }
+
+    /// Replaces the command, description and tags of snippet `id`.
+    fn replace(&self, id: u64, draft: &Draft) -> Result<Snippet> {
+        let dangerous = !safety::check(&draft.command, self.rules()).is_empty();
+        let mut outcome = Err(Error::NotFound(id));
+        self.modify("edit", &mut |snippets| {
+            let Some(stored) = snippets.iter_mut().find(|s| s.id == id) else {
+                return false;
+            };
+            stored.command = draft.command.clone();
+            stored.description = draft.description.clone();
+            stored.tags = draft.tags.clone();
+            stored.dangerous = dangerous;
+            stored.updated = snippet::now();
+            outcome = Ok(stored.clone());
+            true
+        })?;
+        outcome
+    }
+
+    /// Deletes the snippets with these ids and returns how many there were.
+    fn remove(&self, ids: &[u64]) -> Result<usize> {
+        let mut removed = 0;
+        self.modify("delete", &mut |snippets| {
+            let before = snippets.len();
+            snippets.retain(|s| !ids.contains(&s.id));
+            removed = before - snippets.len();
+            removed > 0
+        })?;
+        Ok(removed)
+    }
+}
+
+/// The store file the command-line tool uses, with its lock and operation
+/// log next to it.
+pub struct FileStore {
+    path: String,
+    rules: Vec<Rule>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<String>) -> FileStore {
+        FileStore { path: path.into(), rules: Vec::new() }
+    }
+
+    pub fn with_rules(mut self, rules: Vec<Rule>) -> FileStore {
+        self.rules = rules;
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl SnippetStore for FileStore {
+    fn snippets(&self) -> Result<Vec<Snippet>> {
+        load(&self.path)
+    }
+
+    fn modify(&self, op: &str, change: &mut dyn FnMut(&mut Vec<Snippet>) -> bool) -> Result<bool> {
+        Ok(update(&self.path, op, |snippets| change(snippets).then_some(()))?.is_some())
+    }
+
+    fn record_use(&self, id: u64, status: Option<i32>) -> Result<()> {
+        update_stats(&self.path, |snippets| {
+            let snippet = snippets.iter_mut().find(|s| s.id == id)?;
+            let now = snippet::now();
+            snippet.last_used = Some(now);
+            snippet.use_count += 1;
+            if status.is_some() {
+                snippet.last_run = Some(now);
+                snippet.last_status = status;
+            }
+            Some(())
+        })
+    }
+
+    fn undo(&self, count: usize) -> Result<Vec<Operation>> {
+        oplog::undo(&self.path, count)
+    }
+
+    fn next_id(&self, snippets: &[Snippet]) -> Result<u64> {
+        Ok(next_id(snippets).max(oplog::max_id(&self.path)? + 1))
+    }
+
+    fn versions(&self, id: u64) -> Result<Vec<Version>> {
+        oplog::versions(&self.path, id)
+    }
+
+    fn restore(&self, version: &Snippet) -> Result<()> {
+        oplog::restore(&self.path, version)
+    }
+
+    fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_file(name: &str, contents: &[u8]) -> (std::path::PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("oneliners-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snippets").to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn reads_records_after_the_header() {
+        let contents = "\n#oneliners v1\n{\"id\":3,\"command\":\"du -sh *\",\"description\":\"disk usage\",\"tags\":[\"disk\"],\"created\":1700000000,\"updated\":1700000100}\n\n{\"id\":5,\"command\":\"ls\",\"created\":1,\"updated\":2,\"use_count\":4}\r\n";
+        let (dir, path) = scratch_file("records", contents.as_bytes());
+        assert_eq!(detect_format(&path).unwrap(), Format::Records(1));
+
+        let snippets = load(&path).unwrap();
+        assert_eq!(snippets.len(), 2);
+        assert_eq!((snippets[0].id, snippets[0].command.as_str()), (3, "du -sh *"));
+        assert_eq!(snippets[0].description.as_deref(), Some("disk usage"));
+        assert_eq!(snippets[0].tags, ["disk"]);
+        assert_eq!((snippets[0].created, snippets[0].updated), (1700000000, 1700000100));
+        assert_eq!((snippets[1].id, snippets[1].use_count, snippets[1].last_used), (5, 4, None));
+
+        save(&path, &snippets).unwrap();
+        assert_eq!(load(&path).unwrap(), snippets);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("#oneliners v1\n{\"id\":3,"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reads_legacy_lines_as_snippets() {
+        let (dir, path) = scratch_file("legacy", b"ls -la\n\n  du -sh *  \r\nls -la\n");
+        assert_eq!(detect_format(&path).unwrap(), Format::Legacy);
+
+        let snippets = load(&path).unwrap();
+        let commands: Vec<(u64, &str)> = snippets.iter().map(|s| (s.id, s.command.as_str())).collect();
+        assert_eq!(commands, [(1, "ls -la"), (2, "du -sh *"), (3, "ls -la")]);
+        assert!(snippets.iter().all(|s| s.created == modified_secs(&path) && s.updated == s.created));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_and_blank_files_are_empty() {
+        let (dir, path) = scratch_file("empty", b"\n  \n");
+        assert_eq!(detect_format(&path).unwrap(), Format::Empty);
+        assert!(load(&path).unwrap().is_empty());
+        assert!(load(dir.join("missing").to_str().unwrap()).unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reports_bad_lines_with_their_number() {
+        let (dir, path) = scratch_file("utf8", b"ls\necho \xff\n");
+        assert!(matches!(load(&path), Err(Error::Parse { line: Some(2), message, .. }) if message == "invalid UTF-8"));
+
+        let records = "#oneliners v1\n{\"id\":1,\"command\":\"ls\",\"created\":1,\"updated\":1}\n\n{\"id\":2,\"command\":\n";
+        fs::write(&path, records).unwrap();
+        assert!(matches!(load(&path), Err(Error::Parse { line: Some(4), .. })));
+
+        fs::write(&path, "#oneliners v2\n").unwrap();
+        let error = load(&path).unwrap_err();
+        assert_eq!(error.to_string(), format!("{}: store uses format v2, this build only understands up to v1", path));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn snippet(id: u64, command: &str, tags: &[&str], created: u64, last_used: Option<u64>, use_count: u64) -> Snippet {
+        let mut snippet = Snippet::new(id, command);
+        snippet.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        snippet.created = created;
+        snippet.last_used = last_used;
+        snippet.use_count = use_count;
+        snippet
+    }
+
+    fn listing_sample() -> Vec<Snippet> {
+        vec![
+            snippet(1, "kubectl get pods", &["k8s"], 300, Some(50), 2),
+            snippet(2, "Docker ps", &["docker"], 100, None, 0),
+            snippet(3, "apt update", &["apt", "sudo"], 200, Some(90), 7),
+            snippet(4, "docker images", &["docker"], 100, None, 2),
+            snippet(5, "ls", &[], 400, Some(90), 1),
+        ]
+    }
+
+    fn ids(listing: &Listing) -> Vec<u64> {
+        listing.apply(listing_sample()).iter().map(|s| s.id).collect()
+    }
+
+    #[test]
+    fn listing_sorts_with_ids_breaking_ties() {
+        let listing = |sort| Listing { sort, ..Default::default() };
+        assert_eq!(ids(&listing(SortKey::Created)), [2, 4, 3, 1, 5]);
+        // Never used sorts after everything used, most recent first.
+        assert_eq!(ids(&listing(SortKey::LastUsed)), [3, 5, 1, 2, 4]);
+        assert_eq!(ids(&listing(SortKey::UseCount)), [3, 1, 4, 5, 2]);
+        assert_eq!(ids(&listing(SortKey::Alpha)), [3, 4, 2, 1, 5]);
+        assert_eq!(ids(&Listing { sort: SortKey::LastUsed, reverse: true, ..Default::default() }), [4, 2, 1, 5, 3]);
+    }
+
+    #[test]
+    fn listing_filters_by_every_tag_then_pages() {
+        assert_eq!(ids(&Listing { tags: vec!["docker".to_string()], ..Default::default() }), [2, 4]);
+        assert_eq!(ids(&Listing { tags: vec!["apt".to_string(), "sudo".to_string()], ..Default::default() }), [3]);
+        assert!(ids(&Listing { tags: vec!["apt".to_string(), "docker".to_string()], ..Default::default() }).is_empty());
+
+        assert_eq!(ids(&Listing { limit: Some(2), ..Default::default() }), [2, 4]);
+        assert_eq!(ids(&Listing { offset: 3, ..Default::default() }), [1, 5]);
+        assert_eq!(ids(&Listing { offset: 1, limit: Some(2), reverse: true, ..Default::default() }), [1, 3]);
+        assert!(ids(&Listing { offset: 9, ..Default::default() }).is_empty());
+        assert!(ids(&Listing { limit: Some(0), ..Default::default() }).is_empty());
+    }
+
+    #[test]
+    fn sort_keys_round_trip_through_their_names() {
+        for key in SortKey::ALL {
+            assert_eq!(key.name().parse::<SortKey>().unwrap(), key);
+        }
+        assert_eq!("newest".parse::<SortKey>().unwrap_err().to_string(), "unknown sort order 'newest'");
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::AsRawFd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    Unknown,
+}
+
+/// Decodes raw terminal input into keys. Only the escape sequences that
+/// xterm-compatible terminals send for the keys above are recognised.
+pub struct KeyReader<R: Read> {
+    input: R,
+    pending: VecDeque<Key>,
+}
+
+impl<R: Read> KeyReader<R> {
+    pub fn new(input: R) -> KeyReader<R> {
+        KeyReader { input, pending: VecDeque::new() }
+    }
+
+    /// Returns the next key, or `None` once the input is exhausted.
+    pub fn next_key(&mut self) -> io::Result<Option<Key>> {
+        if let Some(key) = self.pending.pop_front() {
+            return Ok(Some(key));
+        }
+
+        // An escape sequence arrives in a single read, so a lone ESC at the
+        // end of a chunk is the Escape key itself.
+        let mut buf = [0u8; 256];
+        let n = loop {
+            match self.input.read(&mut buf) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if n == 0 {
+            return Ok(None);
+        }
+
+        let mut bytes = &buf[..n];
+        while !bytes.is_empty() {
+            let (key, used) = decode_one(bytes);
+            self.pending.push_back(key);
+            bytes = &bytes[used..];
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+fn decode_one(bytes: &[u8]) -> (Key, usize) {
+    match bytes[0] {
+        0x1b => decode_escape(bytes),
+        b'\r' | b'\n' => (Key::Enter, 1),
+        b'\t' => (Key::Tab, 1),
+        0x7f | 0x08 => (Key::Backspace, 1),
+        b @ 0x01..=0x1a => (Key::Ctrl((b'a' + b - 1) as char), 1),
+        0x00..=0x1f => (Key::Unknown, 1),
+        _ => decode_utf8(bytes),
+    }
+}
+
+fn decode_escape(bytes: &[u8]) -> (Key, usize) {
+    match bytes.get(1) {
+        None => (Key::Esc, 1),
+        Some(b'[') | Some(b'O') => {
+            let Some(end) = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)).map(|p| p + 2) else {
+                return (Key::Unknown, bytes.len());
+            };
+            let key = match (bytes[end], &bytes[2..end]) {
+                (b'A', _) => Key::Up,
+                (b'B', _) => Key::Down,
+                (b'C', _) => Key::Right,
+                (b'D', _) => Key::Left,
+                (b'H', _) => Key::Home,
+                (b'F', _) => Key::End,
+                (b'Z', _) => Key::BackTab,
+                (b'~', b"1") | (b'~', b"7") => Key::Home,
+                (b'~', b"4") | (b'~', b"8") => Key::End,
+                (b'~', b"3") => Key::Delete,
+                (b'~', b"5") => Key::PageUp,
+                (b'~', b"6") => Key::PageDown,
+                _ => Key::Unknown,
+            };
+            (key, end + 1)
+        }
+        // Alt+key: report the key and drop the modifier.
+        Some(_) => {
+            let (key, used) = decode_one(&bytes[1..]);
+            (key, used + 1)
+        }
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> (Key, usize) {
+    let len = match bytes[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return (Key::Unknown, 1),
+    };
+    match bytes.get(..len).and_then(|b| std::str::from_utf8(b).ok()) {
+        Some(s) => (s.chars().next().map(Key::Char).unwrap_or(Key::Unknown), len),
+        None => (Key::Unknown, len.min(bytes.len())),
+    }
+}
+
+/// Columns and rows of the terminal behind `fd`, falling back to 80x24
+/// when the size is unknown.
+pub fn size(fd: &impl AsRawFd) -> (usize, usize) {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) } == 0;
+    if ok && ws.ws_col > 0 && ws.ws_row > 0 {
+        (ws.ws_col as usize, ws.ws_row as usize)
+    } else {
+        (80, 24)
+    }
+}
+
+/// The controlling terminal, opened directly so the picker keeps working
+/// when stdin or stdout are redirected. Raw mode and the alternate screen
+/// are switched on when it is opened and restored when it is dropped.
+pub struct Tty {
+    file: File,
+    saved: libc::termios,
+}
+
+impl Tty {
+    pub fn open() -> io::Result<Tty> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let fd = file.as_raw_fd();
+
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = saved;
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL | libc::BRKINT | libc::INPCK | libc::ISTRIP);
+        raw.c_oflag &= !libc::OPOST;
+        raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut tty = Tty { file, saved };
+        tty.write_all(b"\x1b[?1049h\x1b[H\x1b[2J")?;
+        tty.flush()?;
+        Ok(tty)
+    }
+
+    pub fn try_clone(&self) -> io::Result<File> {
+        self.file.try_clone()
+    }
+}
+
+impl Write for Tty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        let _ = self.file.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = self.file.flush();
+        unsafe {
+            libc::tcsetattr(self.file.as_raw_fd(), libc::TCSAFLUSH, &self.saved);
+        }
+    }
+}
+
+/// Shows `text` through `$PAGER` (`less` by default) when it doesn't fit
+/// on the terminal, and prints it as it is otherwise.
+pub fn page(text: &str) {
+    let stdout = io::stdout();
+    if !stdout.is_terminal() || text.lines().count() < size(&stdout).1 {
+        print!("{}", text);
+        return;
+    }
+
+    let pager = std::env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty()).unwrap_or_else(|| "less".to_string());
+    let mut command = std::process::Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(std::process::Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        // Quit when the text fits after all, keep colors, don't clear the screen.
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => {
+            print!("{}", text);
+            return;
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out one chunk per read, like a terminal delivering keystrokes.
+    struct Chunks(VecDeque<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(chunk) = self.0.pop_front() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    fn keys(chunks: &[&'static [u8]]) -> Vec<Key> {
+        let mut reader = KeyReader::new(Chunks(chunks.iter().copied().collect()));
+        let mut keys = Vec::new();
+        while let Some(key) = reader.next_key().unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn decodes_plain_and_control_keys() {
+        assert_eq!(
+            keys(&[b"a\r\t\x7f\x08\x01\x17\x00\n"]),
+            [
+                Key::Char('a'),
+                Key::Enter,
+                Key::Tab,
+                Key::Backspace,
+                Key::Backspace,
+                Key::Ctrl('a'),
+                Key::Ctrl('w'),
+                Key::Unknown,
+                Key::Enter
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_escape_sequences() {
+        assert_eq!(
+            keys(&[b"\x1b[A\x1b[B\x1bOC\x1b[D\x1b[H\x1b[4~\x1b[Z\x1b[5~\x1b[6~\x1b[3~\x1b[1;5A\x1b[99~"]),
+            [
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Home,
+                Key::End,
+                Key::BackTab,
+                Key::PageUp,
+                Key::PageDown,
+                Key::Delete,
+                Key::Up,
+                Key::Unknown
+            ]
+        );
+    }
+
+    #[test]
+    fn a_lone_escape_is_the_escape_key() {
+        assert_eq!(keys(&[b"\x1b", b"q"]), [Key::Esc, Key::Char('q')]);
+        // Alt+key drops the modifier.
+        assert_eq!(keys(&[b"\x1bx"]), [Key::Char('x')]);
+    }
+
+    #[test]
+    fn decodes_multibyte_characters() {
+        assert_eq!(keys(&["é✓🦀".as_bytes()]), [Key::Char('é'), Key::Char('✓'), Key::Char('🦀')]);
+        assert_eq!(keys(&[b"\xff", b"\xc3"]), [Key::Unknown, Key::Unknown]);
+    }
+}
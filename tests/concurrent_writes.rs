@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+const WRITERS: usize = 8;
+const SNIPPETS_PER_WRITER: usize = 15;
+
+fn scratch_home(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("oneliners-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn oneliners(home: &Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_oneliners"))
+        .args(args)
+        .env("HOME", home)
+        .env("ONELINERS_FILE", home.join("snippets"))
+        .env_remove("ONELINERS_CLIPBOARD")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn parallel_writers_never_lose_or_corrupt_snippets() {
+    let home = scratch_home("stress");
+
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|writer| {
+            let home = home.clone();
+            thread::spawn(move || {
+                for n in 0..SNIPPETS_PER_WRITER {
+                    let command = format!("echo writer-{} snippet-{}", writer, n);
+                    let output = oneliners(&home, &["store", &command]);
+                    assert!(output.status.success(), "store failed: {:?}", output);
+                    // Recording a run rewrites the store while others add to it.
+                    if n % 5 == 4 {
+                        let output = oneliners(&home, &["run", "--exact", &command]);
+                        assert!(output.status.success(), "run failed: {:?}", output);
+                    }
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let contents = fs::read_to_string(home.join("snippets")).unwrap();
+    let mut lines = contents.lines();
+    assert_eq!(lines.next(), Some("#oneliners v1"));
+
+    let mut ids = HashSet::new();
+    let mut commands = HashSet::new();
+    for line in lines {
+        let record: serde_json::Value = serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line));
+        assert!(ids.insert(record["id"].as_u64().unwrap()), "duplicate id in {}", line);
+        commands.insert(record["command"].as_str().unwrap().to_string());
+    }
+    for writer in 0..WRITERS {
+        for n in 0..SNIPPETS_PER_WRITER {
+            assert!(commands.contains(&format!("echo writer-{} snippet-{}", writer, n)));
+        }
+    }
+    assert_eq!(commands.len(), WRITERS * SNIPPETS_PER_WRITER);
+
+    let leftovers: Vec<_> = fs::read_dir(&home)
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.contains(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty(), "temporary files left behind: {:?}", leftovers);
+
+    let _ = fs::remove_dir_all(&home);
+}
@@ -0,0 +1,127 @@
+use oneliners::{Draft, Error, FileStore, SnippetStore};
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch_store(name: &str) -> (PathBuf, FileStore) {
+    let dir = std::env::temp_dir().join(format!("oneliners-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let store = FileStore::new(dir.join("snippets").to_str().unwrap());
+    (dir, store)
+}
+
+fn draft(command: &str) -> Draft {
+    Draft { command: command.to_string(), ..Default::default() }
+}
+
+fn commands(store: &FileStore) -> Vec<(u64, String)> {
+    store.snippets().unwrap().into_iter().map(|s| (s.id, s.command)).collect()
+}
+
+#[test]
+fn ids_of_deleted_snippets_are_not_reused() {
+    let (dir, store) = scratch_store("ids");
+    store.add(&draft("echo a")).unwrap();
+    store.add(&draft("echo b")).unwrap();
+    assert_eq!(store.remove(&[2]).unwrap(), 1);
+    let c = store.add(&draft("echo c")).unwrap();
+    assert_eq!(c.id, 3);
+
+    let history: Vec<(String, Option<String>)> =
+        store.versions(2).unwrap().into_iter().map(|v| (v.op, v.snippet.map(|s| s.command))).collect();
+    assert_eq!(history, [("store".to_string(), Some("echo b".to_string())), ("delete".to_string(), None)]);
+    assert_eq!(store.versions(3).unwrap().len(), 1);
+
+    // Undoing a store frees nothing either.
+    store.undo(1).unwrap();
+    assert_eq!(store.add(&draft("echo d")).unwrap().id, 4);
+    assert_eq!(store.import(&["echo e".to_string(), "echo a".to_string()]).unwrap()[0].id, 5);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn restore_brings_back_a_deleted_snippet() {
+    let (dir, store) = scratch_store("restore");
+    store.add(&draft("echo a")).unwrap();
+    store.add(&draft("echo b")).unwrap();
+    store.remove(&[2]).unwrap();
+    store.add(&draft("echo c")).unwrap();
+
+    let versions = store.versions(2).unwrap();
+    store.restore(versions[0].snippet.as_ref().unwrap()).unwrap();
+    assert_eq!(commands(&store), [(1, "echo a".to_string()), (2, "echo b".to_string()), (3, "echo c".to_string())]);
+    assert_eq!(store.versions(2).unwrap().last().unwrap().op, "restore");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn restore_keeps_usage_statistics() {
+    let (dir, store) = scratch_store("restore-stats");
+    let a = store.add(&draft("echo a")).unwrap();
+    store.replace(a.id, &draft("echo edited")).unwrap();
+    store.record_use(a.id, Some(0)).unwrap();
+
+    let original = store.versions(a.id).unwrap()[0].snippet.clone().unwrap();
+    store.restore(&original).unwrap();
+    let restored = store.get(a.id).unwrap();
+    assert_eq!(restored.command, "echo a");
+    assert_eq!(restored.use_count, 1);
+    assert_eq!(restored.last_status, Some(0));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn undo_reverts_the_latest_changes_in_turn() {
+    let (dir, store) = scratch_store("undo");
+    store.add(&draft("echo a")).unwrap();
+    store.add(&draft("echo b")).unwrap();
+    store.replace(1, &draft("echo edited")).unwrap();
+    store.remove(&[2]).unwrap();
+
+    let undone = store.undo(1).unwrap();
+    assert_eq!(undone.iter().map(|op| op.op.as_str()).collect::<Vec<_>>(), ["delete"]);
+    assert_eq!(commands(&store), [(1, "echo edited".to_string()), (2, "echo b".to_string())]);
+
+    // A second undo goes further back instead of undoing the undo.
+    let undone = store.undo(2).unwrap();
+    assert_eq!(undone.iter().map(|op| op.op.as_str()).collect::<Vec<_>>(), ["edit", "store"]);
+    assert_eq!(commands(&store), [(1, "echo a".to_string())]);
+
+    store.undo(5).unwrap();
+    assert!(commands(&store).is_empty());
+    assert!(store.undo(1).unwrap().is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_corrupt_log_leaves_the_store_untouched() {
+    let (dir, store) = scratch_store("corrupt-log");
+    store.add(&draft("echo a")).unwrap();
+    store.add(&draft("echo b")).unwrap();
+    let store_path = dir.join("snippets");
+    let log_path = dir.join("snippets.log");
+    let mut log = fs::read_to_string(&log_path).unwrap();
+    log.push_str("{not json\n");
+    fs::write(&log_path, &log).unwrap();
+    let contents = fs::read_to_string(&store_path).unwrap();
+
+    let error = store.remove(&[2]).unwrap_err();
+    assert!(matches!(error, Error::Parse { line: Some(3), .. }), "{}", error);
+    assert!(store.add(&draft("echo c")).is_err());
+    assert!(store.replace(1, &draft("echo edited")).is_err());
+    assert!(store.undo(1).is_err());
+    assert_eq!(fs::read_to_string(&store_path).unwrap(), contents);
+    assert_eq!(fs::read_to_string(&log_path).unwrap(), log);
+
+    // Once the log is repaired, nothing was lost.
+    fs::write(&log_path, log.replace("{not json\n", "")).unwrap();
+    store.remove(&[2]).unwrap();
+    store.undo(1).unwrap();
+    assert_eq!(commands(&store), [(1, "echo a".to_string()), (2, "echo b".to_string())]);
+
+    let _ = fs::remove_dir_all(&dir);
+}